@@ -12,13 +12,17 @@ use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
 
 use crate::api::{
-    face_api::{FaceExpression, get_face, set_face},
+    face_api::{FaceExpression, LastFaceUpdateTime, get_face, set_face},
     hands_api::{CurrentHands, LastHandsUpdateTime, get_hands, set_hands},
     pose_api::LastPoseUpdateTime,
     face_api::CurrentFace,
+    ingest::{ActiveSource, IngestError},
 };
 
 use super::pose_api::{CurrentPose, get_pose, set_pose};
+use super::session_api::{start_recording, stop_recording};
+use super::stream_api::stream;
+use super::take_api::{start_take, stop_take};
 pub struct MocapApiPlugin;
 
 impl Plugin for MocapApiPlugin {
@@ -40,7 +44,9 @@ impl Plugin for MocapApiPlugin {
         app.init_resource::<CurrentFace>();
         app.init_resource::<LastPoseUpdateTime>();
         app.init_resource::<LastHandsUpdateTime>();
+        app.init_resource::<LastFaceUpdateTime>();
         app.init_resource::<FaceExpression>();
+        app.init_resource::<ActiveSource>();
 
         app.add_plugins(bevy_webserver::BevyWebServerPlugin);
         app.route("/", axum::routing::get(index))
@@ -50,6 +56,11 @@ impl Plugin for MocapApiPlugin {
             .route("/get_hands", axum::routing::get(get_hands))
             .route("/set_face", axum::routing::post(set_face))
             .route("/get_face", axum::routing::get(get_face))
+            .route("/stream", axum::routing::get(stream))
+            .route("/start_recording", axum::routing::post(start_recording))
+            .route("/stop_recording", axum::routing::post(stop_recording))
+            .route("/start_take", axum::routing::post(start_take))
+            .route("/stop_take", axum::routing::post(stop_take))
             .route("/pair", axum::routing::get(pair));
 
         app.layer(TraceLayer::new_for_http());
@@ -69,6 +80,7 @@ fn hot_index() -> axum::response::Html<String> {
             <body>
                 <p>Rendering server is running...</p>
                 <p><a href="/get_pose">Current Pose</a></p>
+                <p>WebSocket stream: <code>/stream</code></p>
             </body>
         </html>
         "#
@@ -94,3 +106,14 @@ pub fn internal_error(message: &str) -> axum::response::Response {
     )
         .into_response()
 }
+
+pub fn ingest_error(err: IngestError) -> axum::response::Response {
+    match err {
+        IngestError::StaleFrame { .. } => (
+            StatusCode::CONFLICT,
+            Json(json!({ "error": err.to_string() })),
+        )
+            .into_response(),
+        IngestError::Internal(message) => internal_error(&message),
+    }
+}
@@ -1,10 +1,14 @@
 use std::ops::{Add, Mul, Sub};
 use std::time::Instant;
 
-use crate::api::api_server::internal_error;
+use crate::api::api_server::{ingest_error, internal_error};
+use crate::api::ingest::{FrameClock, IngestError, live_input_active};
 use crate::api::pose_api::LandmarkJson;
-use crate::math::kalman_filter::VelocityKalman;
+use crate::api::stream_api::StreamMessage;
 use crate::math::landmarks::{LandmarkIndex, Landmarks};
+use crate::math::filter::{Elements, Magnitude};
+use crate::math::filter::{FilterChain, FilterConfig, SmoothingFilter};
+use crate::session::recorder::{clone_if_recording, record_frame};
 use crate::ui::state::GuiState;
 use axum::Json;
 use axum::http::StatusCode;
@@ -58,6 +62,9 @@ pub struct FaceLandmarkerResultJson {
 pub struct FaceLandmarkerResult {
     #[serde(rename = "faceLandmarkerResult")]
     pub face_landmarker_result: FaceLandmarkerResultJson,
+    /// Capture time of the video frame in milliseconds, if the client provides one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<f64>,
 }
 
 #[derive(Resource, Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
@@ -119,77 +126,240 @@ pub struct FaceExpression {
     pub look_y: f32,
 }
 
+/// Maps the MediaPipe / ARKit blendshape names onto the fields of [`FaceExpression`].
+macro_rules! blendshapes {
+    ($($name:literal => $field:ident),* $(,)?) => {
+        impl FaceExpression {
+            /// Sets a blendshape by its MediaPipe name (e.g. `"jawOpen"`).
+            /// Returns `false` if the name is unknown.
+            pub fn set_blendshape(&mut self, name: &str, value: f32) -> bool {
+                match name {
+                    $($name => self.$field = value,)*
+                    _ => return false,
+                }
+                true
+            }
+
+            /// A blendshape by its MediaPipe name, `None` if the name is unknown.
+            pub fn blendshape(&self, name: &str) -> Option<f32> {
+                match name {
+                    $($name => Some(self.$field),)*
+                    _ => None,
+                }
+            }
+
+            /// Every blendshape value with its MediaPipe name.
+            pub fn blendshapes(&self) -> impl Iterator<Item = (&'static str, f32)> {
+                [$(($name, self.$field)),*].into_iter()
+            }
+        }
+
+        impl Add for &FaceExpression {
+            type Output = FaceExpression;
+
+            fn add(self, rhs: Self) -> Self::Output {
+                FaceExpression {
+                    $($field: self.$field + rhs.$field,)*
+                    look_x: self.look_x + rhs.look_x,
+                    look_y: self.look_y + rhs.look_y,
+                }
+            }
+        }
+
+        impl Sub for &FaceExpression {
+            type Output = FaceExpression;
+
+            fn sub(self, rhs: Self) -> Self::Output {
+                FaceExpression {
+                    $($field: self.$field - rhs.$field,)*
+                    look_x: self.look_x - rhs.look_x,
+                    look_y: self.look_y - rhs.look_y,
+                }
+            }
+        }
+
+        impl Mul<f32> for &FaceExpression {
+            type Output = FaceExpression;
+
+            fn mul(self, rhs: f32) -> Self::Output {
+                FaceExpression {
+                    $($field: self.$field * rhs,)*
+                    look_x: self.look_x * rhs,
+                    look_y: self.look_y * rhs,
+                }
+            }
+        }
+
+        /// Every blendshape is an element, followed by `look_x` and `look_y`.
+        impl Elements for FaceExpression {
+            fn element_count(&self) -> usize {
+                [$(self.$field),*].len() + 2
+            }
+
+            fn element_magnitude(&self, i: usize) -> f32 {
+                [$(self.$field,)* self.look_x, self.look_y][i].abs()
+            }
+
+            fn scale_elements(&self, factors: &[f32]) -> Self {
+                let mut factors = factors.iter().copied();
+                let mut next = || factors.next().unwrap_or(1.0);
+                // Struct expressions evaluate their fields in the order they are written.
+                FaceExpression {
+                    $($field: self.$field * next(),)*
+                    look_x: self.look_x * next(),
+                    look_y: self.look_y * next(),
+                }
+            }
+        }
+
+        /// The fastest blendshape sets the speed, so a blink is not smoothed away.
+        impl Magnitude for FaceExpression {
+            fn magnitude(&self) -> f32 {
+                [$(self.$field.abs()),*].into_iter().fold(0.0, f32::max)
+            }
+        }
+    };
+}
+
+blendshapes! {
+    "_neutral" => neutral,
+    "browDownLeft" => brow_down_left,
+    "browDownRight" => brow_down_right,
+    "browInnerUp" => brow_inner_up,
+    "browOuterUpLeft" => brow_outer_up_left,
+    "browOuterUpRight" => brow_outer_up_right,
+    "cheekPuff" => cheek_puff,
+    "cheekSquintLeft" => cheek_squint_left,
+    "cheekSquintRight" => cheek_squint_right,
+    "eyeBlinkLeft" => eye_blink_left,
+    "eyeBlinkRight" => eye_blink_right,
+    "eyeLookDownLeft" => eye_look_down_left,
+    "eyeLookDownRight" => eye_look_down_right,
+    "eyeLookInLeft" => eye_look_in_left,
+    "eyeLookInRight" => eye_look_in_right,
+    "eyeLookOutLeft" => eye_look_out_left,
+    "eyeLookOutRight" => eye_look_out_right,
+    "eyeLookUpLeft" => eye_look_up_left,
+    "eyeLookUpRight" => eye_look_up_right,
+    "eyeSquintLeft" => eye_squint_left,
+    "eyeSquintRight" => eye_squint_right,
+    "eyeWideLeft" => eye_wide_left,
+    "eyeWideRight" => eye_wide_right,
+    "jawForward" => jaw_forward,
+    "jawLeft" => jaw_left,
+    "jawOpen" => jaw_open,
+    "jawRight" => jaw_right,
+    "mouthClose" => mouth_close,
+    "mouthDimpleLeft" => mouth_dimple_left,
+    "mouthDimpleRight" => mouth_dimple_right,
+    "mouthFrownLeft" => mouth_frown_left,
+    "mouthFrownRight" => mouth_frown_right,
+    "mouthFunnel" => mouth_funnel,
+    "mouthLeft" => mouth_left,
+    "mouthLowerDownLeft" => mouth_lower_down_left,
+    "mouthLowerDownRight" => mouth_lower_down_right,
+    "mouthPressLeft" => mouth_press_left,
+    "mouthPressRight" => mouth_press_right,
+    "mouthPucker" => mouth_pucker,
+    "mouthRight" => mouth_right,
+    "mouthRollLower" => mouth_roll_lower,
+    "mouthRollUpper" => mouth_roll_upper,
+    "mouthShrugLower" => mouth_shrug_lower,
+    "mouthShrugUpper" => mouth_shrug_upper,
+    "mouthSmileLeft" => mouth_smile_left,
+    "mouthSmileRight" => mouth_smile_right,
+    "mouthStretchLeft" => mouth_stretch_left,
+    "mouthStretchRight" => mouth_stretch_right,
+    "mouthUpperUpLeft" => mouth_upper_up_left,
+    "mouthUpperUpRight" => mouth_upper_up_right,
+    "noseSneerLeft" => nose_sneer_left,
+    "noseSneerRight" => nose_sneer_right,
+}
+
+impl FaceExpression {
+    /// Recomputes `look_x`/`look_y` from the eye look blendshapes.
+    pub fn update_look(&mut self) {
+        // Y-axis (Vertical): Average of "up" scores minus average of "down" scores.
+        self.look_y = (self.eye_look_up_left + self.eye_look_up_right) / 2.0
+            - (self.eye_look_down_left + self.eye_look_down_right) / 2.0;
+
+        // X-axis (Horizontal): Looking right (eyeLookInLeft, eyeLookOutRight) minus looking left.
+        let look_right_score = (self.eye_look_in_left + self.eye_look_out_right) / 2.0;
+        let look_left_score = (self.eye_look_out_left + self.eye_look_in_right) / 2.0;
+        self.look_x = look_right_score - look_left_score;
+    }
+}
+
+/// A vowel mouth shape, made of MediaPipe blendshapes.
+#[derive(Debug, Clone, Copy)]
+pub struct Viseme {
+    /// VRM 1.0 preset expression name.
+    pub vrm: &'static str,
+    /// VRM 0.x preset name, also used by the VMC protocol.
+    pub vmc: &'static str,
+    pub blendshapes: &'static [&'static str],
+}
+
+/// The one mapping between visemes and blendshapes, so a face read from VMC drives a VRM model
+/// the same way as a face tracked by MediaPipe.
+pub const VISEMES: [Viseme; 5] = [
+    Viseme {
+        vrm: "aa",
+        vmc: "A",
+        blendshapes: &["jawOpen"],
+    },
+    Viseme {
+        vrm: "ih",
+        vmc: "I",
+        blendshapes: &["mouthUpperUpLeft", "mouthUpperUpRight"],
+    },
+    Viseme {
+        vrm: "ou",
+        vmc: "U",
+        blendshapes: &["mouthPucker"],
+    },
+    Viseme {
+        vrm: "ee",
+        vmc: "E",
+        blendshapes: &["mouthStretchLeft", "mouthStretchRight"],
+    },
+    Viseme {
+        vrm: "oh",
+        vmc: "O",
+        blendshapes: &["mouthFunnel"],
+    },
+];
+
+impl Viseme {
+    /// How far `face` makes this viseme, the average of its blendshapes.
+    pub fn weight(&self, face: &FaceExpression) -> f32 {
+        let sum: f32 = self
+            .blendshapes
+            .iter()
+            .filter_map(|name| face.blendshape(name))
+            .sum();
+        sum / self.blendshapes.len() as f32
+    }
+
+    /// Sets every blendshape of this viseme, the inverse of [`Viseme::weight`].
+    pub fn apply(&self, face: &mut FaceExpression, weight: f32) {
+        for name in self.blendshapes {
+            face.set_blendshape(name, weight);
+        }
+    }
+}
+
 impl From<&[FaceCategoryJson]> for FaceExpression {
     /// Converts a slice of `BlendshapeCategory` into a `FaceExpression` struct.
     fn from(categories: &[FaceCategoryJson]) -> Self {
         let mut expression = FaceExpression::default();
 
         for category in categories {
-            match category.category_name.as_str() {
-                "_neutral" => expression.neutral = category.score,
-                "browDownLeft" => expression.brow_down_left = category.score,
-                "browDownRight" => expression.brow_down_right = category.score,
-                "browInnerUp" => expression.brow_inner_up = category.score,
-                "browOuterUpLeft" => expression.brow_outer_up_left = category.score,
-                "browOuterUpRight" => expression.brow_outer_up_right = category.score,
-                "cheekPuff" => expression.cheek_puff = category.score,
-                "cheekSquintLeft" => expression.cheek_squint_left = category.score,
-                "cheekSquintRight" => expression.cheek_squint_right = category.score,
-                "eyeBlinkLeft" => expression.eye_blink_left = category.score,
-                "eyeBlinkRight" => expression.eye_blink_right = category.score,
-                "eyeLookDownLeft" => expression.eye_look_down_left = category.score,
-                "eyeLookDownRight" => expression.eye_look_down_right = category.score,
-                "eyeLookInLeft" => expression.eye_look_in_left = category.score,
-                "eyeLookInRight" => expression.eye_look_in_right = category.score,
-                "eyeLookOutLeft" => expression.eye_look_out_left = category.score,
-                "eyeLookOutRight" => expression.eye_look_out_right = category.score,
-                "eyeLookUpLeft" => expression.eye_look_up_left = category.score,
-                "eyeLookUpRight" => expression.eye_look_up_right = category.score,
-                "eyeSquintLeft" => expression.eye_squint_left = category.score,
-                "eyeSquintRight" => expression.eye_squint_right = category.score,
-                "eyeWideLeft" => expression.eye_wide_left = category.score,
-                "eyeWideRight" => expression.eye_wide_right = category.score,
-                "jawForward" => expression.jaw_forward = category.score,
-                "jawLeft" => expression.jaw_left = category.score,
-                "jawOpen" => expression.jaw_open = category.score,
-                "jawRight" => expression.jaw_right = category.score,
-                "mouthClose" => expression.mouth_close = category.score,
-                "mouthDimpleLeft" => expression.mouth_dimple_left = category.score,
-                "mouthDimpleRight" => expression.mouth_dimple_right = category.score,
-                "mouthFrownLeft" => expression.mouth_frown_left = category.score,
-                "mouthFrownRight" => expression.mouth_frown_right = category.score,
-                "mouthFunnel" => expression.mouth_funnel = category.score,
-                "mouthLeft" => expression.mouth_left = category.score,
-                "mouthLowerDownLeft" => expression.mouth_lower_down_left = category.score,
-                "mouthLowerDownRight" => expression.mouth_lower_down_right = category.score,
-                "mouthPressLeft" => expression.mouth_press_left = category.score,
-                "mouthPressRight" => expression.mouth_press_right = category.score,
-                "mouthPucker" => expression.mouth_pucker = category.score,
-                "mouthRight" => expression.mouth_right = category.score,
-                "mouthRollLower" => expression.mouth_roll_lower = category.score,
-                "mouthRollUpper" => expression.mouth_roll_upper = category.score,
-                "mouthShrugLower" => expression.mouth_shrug_lower = category.score,
-                "mouthShrugUpper" => expression.mouth_shrug_upper = category.score,
-                "mouthSmileLeft" => expression.mouth_smile_left = category.score,
-                "mouthSmileRight" => expression.mouth_smile_right = category.score,
-                "mouthStretchLeft" => expression.mouth_stretch_left = category.score,
-                "mouthStretchRight" => expression.mouth_stretch_right = category.score,
-                "mouthUpperUpLeft" => expression.mouth_upper_up_left = category.score,
-                "mouthUpperUpRight" => expression.mouth_upper_up_right = category.score,
-                "noseSneerLeft" => expression.nose_sneer_left = category.score,
-                "noseSneerRight" => expression.nose_sneer_right = category.score,
-                _ => {} // Ignore any unknown categories
-            }
+            // Ignore any unknown categories
+            expression.set_blendshape(&category.category_name, category.score);
         }
 
-        // Y-axis (Vertical): Average of "up" scores minus average of "down" scores.
-        expression.look_y = (expression.eye_look_up_left + expression.eye_look_up_right) / 2.0
-            - (expression.eye_look_down_left + expression.eye_look_down_right) / 2.0;
-
-        // X-axis (Horizontal): Looking right (eyeLookInLeft, eyeLookOutRight) minus looking left.
-        let look_right_score = (expression.eye_look_in_left + expression.eye_look_out_right) / 2.0;
-        let look_left_score = (expression.eye_look_out_left + expression.eye_look_in_right) / 2.0;
-        expression.look_x = look_right_score - look_left_score;
+        expression.update_look();
 
         expression
     }
@@ -203,60 +373,97 @@ pub async fn set_face(Json(payload): Json<FaceLandmarkerResult>) -> impl IntoRes
 
 pub struct CurrentFace {
     pub expression: Option<FaceExpression>,
+    /// Smooths the detected blendshapes into `expression`.
+    #[serde(skip)]
+    pub filter: Option<FilterChain<FaceExpression>>,
 }
 
+impl CurrentFace {
+    /// Filters the blendshapes of the first detected face, if any, into the expression.
+    pub fn apply(&mut self, result: &FaceLandmarkerResultJson, dt: f32, config: &FilterConfig) {
+        if let Some(first_face_blendshapes) = result.face_blendshapes.first() {
+            // Convert the list of categories into our new, flat struct.
+            let measured = FaceExpression::from(first_face_blendshapes.categories.as_slice());
+            if let Some(filter) = &mut self.filter {
+                filter.configure(config);
+                filter.update(&measured, dt);
+            } else {
+                self.filter = Some(FilterChain::new(measured, config));
+            }
+            self.expression = self.filter.as_ref().map(|filter| filter.get().clone());
+        }
+    }
+}
+
+#[derive(Resource, Default, Clone, Debug)]
+pub struct LastFaceUpdateTime(pub FrameClock);
+
 #[hot]
 fn set_face_hot(payload: FaceLandmarkerResult) -> impl IntoResponse {
+    match ingest_face(payload) {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => ingest_error(err),
+    }
+}
+
+/// Feeds a face frame into [`CurrentFace`], shared by the HTTP route and the stream socket.
+#[hot]
+pub fn ingest_face(payload: FaceLandmarkerResult) -> Result<(), IngestError> {
     // println!("face: {:?}", payload);
 
-    match AsyncWorld
+    let filter_config = match AsyncWorld
         .resource::<GuiState>()
-        .get(|state| state.update_hands_data)
+        .get(|state| (state.update_hands_data, state.face_filter_config()))
     {
-        Ok(v) => {
+        Ok((v, filter_config)) => {
             if !v {
                 // info!("Not updating pose data");
-                return StatusCode::OK.into_response();
+                return Ok(());
             }
+            filter_config
         }
         Err(err) => {
-            // use crate::api::api_server::internal_error;
-
             let message = format!("Error accessing CurrentPose: {}", err);
             tracing::error!(message);
-            return internal_error(&message);
+            return Err(message.into());
+        }
+    };
+
+    if !live_input_active()? {
+        return Ok(());
+    }
+
+    let recorded = clone_if_recording(&payload);
+
+    // Retrieve and update the last update time, rejecting frames older than the last one
+    let now = Instant::now();
+    let dt = match AsyncWorld.resource::<LastFaceUpdateTime>().get_mut(
+        |last_update_time: &mut LastFaceUpdateTime| {
+            last_update_time.0.tick(payload.timestamp, now)
+        },
+    ) {
+        Ok(dt) => dt?,
+        Err(err) => {
+            let message = format!("Error accessing LastFaceUpdateTime: {}", err);
+            tracing::error!(message);
+            return Err(message.into());
         }
     };
 
     match AsyncWorld
         .resource::<CurrentFace>()
-        .get_mut(|face: &mut CurrentFace| {
-            if let Some(first_face_blendshapes) =
-                payload.face_landmarker_result.face_blendshapes.first()
-            {
-                // Convert the list of categories into our new, flat struct.
-                let expressions =
-                    FaceExpression::from(first_face_blendshapes.categories.as_slice());
-
-                // Now you can access each blendshape by its name!
-                // println!("Jaw Open: {}", expressions.jaw_open);
-                // println!("Mouth Smile Left: {}", expressions.mouth_smile_left);
-                // println!("Eye Blink Right: {}", expressions.eye_blink_right);
-                // println!("Mouth Stretch Right: {}", expressions.mouth_stretch_right);
-
-                // println!(
-                //     "Eye Look Direction (X, Y): ({:.2}, {:.2})",
-                //     expressions.look_x, expressions.look_y
-                // );
-
-                face.expression = Some(expressions);
+        .get_mut(|face: &mut CurrentFace| face.apply(&payload.face_landmarker_result, dt, &filter_config))
+    {
+        Ok(_) => {
+            if let Some(raw) = recorded {
+                record_frame(StreamMessage::Face(raw));
             }
-        }) {
-        Ok(_) => return StatusCode::OK.into_response(),
+            return Ok(());
+        }
         Err(err) => {
             let message = format!("Error accessing CurrentPose: {}", err);
             tracing::error!(message);
-            return internal_error(&message);
+            return Err(message.into());
         }
     }
 }
@@ -279,3 +486,36 @@ async fn get_face_hot() -> impl IntoResponse {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visemes_read_back_what_they_set() {
+        for viseme in VISEMES {
+            let mut face = FaceExpression::default();
+            viseme.apply(&mut face, 0.7);
+            for other in VISEMES {
+                let expected = if other.vrm == viseme.vrm { 0.7 } else { 0.0 };
+                assert_eq!(
+                    other.weight(&face),
+                    expected,
+                    "{} after {}",
+                    other.vrm,
+                    viseme.vrm
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn viseme_blendshapes_exist() {
+        let face = FaceExpression::default();
+        for viseme in VISEMES {
+            for name in viseme.blendshapes {
+                assert!(face.blendshape(name).is_some(), "unknown blendshape {name}");
+            }
+        }
+    }
+}
@@ -1,10 +1,15 @@
 use std::ops::{Add, Mul, Sub};
 use std::time::Instant;
 
-use crate::api::api_server::internal_error;
+use crate::api::api_server::{ingest_error, internal_error};
+use crate::api::ingest::{FrameClock, IngestError, live_input_active};
 use crate::api::pose_api::LandmarkJson;
-use crate::math::kalman_filter::VelocityKalman;
-use crate::math::landmarks::{LandmarkIndex, Landmarks};
+use crate::api::stream_api::StreamMessage;
+use crate::math::coordinate_frame::CoordinateFrame;
+use crate::math::filter::{FilterChain, FilterConfig, SmoothingFilter};
+use crate::math::landmarks::{LandmarkIndex, Landmarks, LandmarksError};
+use crate::math::filter::{Elements, Magnitude};
+use crate::session::recorder::{clone_if_recording, record_frame};
 use crate::ui::state::GuiState;
 use axum::Json;
 use axum::http::StatusCode;
@@ -15,6 +20,7 @@ use bevy_defer::{AsyncAccess, AsyncWorld};
 use bevy_simple_subsecond_system::hot;
 use serde;
 use serde::{Deserialize, Serialize};
+use strum::EnumCount as _;
 use strum_macros::{EnumCount, EnumIter};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumCount, EnumIter, Serialize, Deserialize)]
@@ -68,6 +74,9 @@ pub struct HandLandmarkerResultJson {
 pub struct HandLandmarkerResult {
     #[serde(rename = "handLandmarkerResult")]
     pub hand_landmarker_result: HandLandmarkerResultJson,
+    /// Capture time of the video frame in milliseconds, if the client provides one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<f64>,
 }
 
 #[derive(Resource, Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
@@ -85,7 +94,7 @@ pub async fn set_hands(Json(payload): Json<HandLandmarkerResult>) -> impl IntoRe
     set_hands_hot(payload)
 }
 
-pub type HandLandmarks = Landmarks<HandLandmarkIndex>;
+pub type HandLandmarks = Landmarks<HandLandmarkIndex, { HandLandmarkIndex::COUNT }>;
 impl LandmarkIndex for HandLandmarkIndex {
     fn as_index(self) -> usize {
         self as usize
@@ -135,14 +144,52 @@ impl Mul<f32> for &HandKeyPoints {
     }
 }
 
+/// Measured on the world landmarks, which are in metres.
+impl Magnitude for HandKeyPoints {
+    fn magnitude(&self) -> f32 {
+        self.world_landmarks.magnitude()
+    }
+}
+
+/// The image landmarks, then the world landmarks. MediaPipe does not fill in the visibility
+/// of hand landmarks, so every element is trusted the same.
+impl Elements for HandKeyPoints {
+    fn element_count(&self) -> usize {
+        self.landmarks.element_count() + self.world_landmarks.element_count()
+    }
+
+    fn element_magnitude(&self, i: usize) -> f32 {
+        let image = self.landmarks.element_count();
+        if i < image {
+            self.landmarks.element_magnitude(i)
+        } else {
+            self.world_landmarks.element_magnitude(i - image)
+        }
+    }
+
+    fn scale_elements(&self, factors: &[f32]) -> Self {
+        let (image, world) = factors.split_at(self.landmarks.element_count());
+        HandKeyPoints {
+            landmarks: self.landmarks.scale_elements(image),
+            world_landmarks: self.world_landmarks.scale_elements(world),
+            handedness: self.handedness,
+        }
+    }
+}
+
 #[derive(Resource, Default, Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct CurrentHands {
-    pub left_hand: Option<VelocityKalman<HandKeyPoints>>,
-    pub right_hand: Option<VelocityKalman<HandKeyPoints>>,
+    pub left_hand: Option<FilterChain<HandKeyPoints>>,
+    pub right_hand: Option<FilterChain<HandKeyPoints>>,
 }
 
-impl From<HandLandmarkerResultJson> for CurrentHands {
-    fn from(value: HandLandmarkerResultJson) -> Self {
+impl CurrentHands {
+    /// Starts a filter for each detected hand, with the landmarks laid out as `frame`.
+    pub fn from_json(
+        value: HandLandmarkerResultJson,
+        frame: &CoordinateFrame,
+        config: &FilterConfig,
+    ) -> Result<Self, LandmarksError> {
         let left_hand = match value.landmarks.get(0) {
             Some(v) => {
                 info!("Detected left hand");
@@ -154,19 +201,21 @@ impl From<HandLandmarkerResultJson> for CurrentHands {
                         .and_then(|v| v.get(0))
                         .map_or(0.0, |v| v.score), // Using map_or is slightly cleaner
                 };
-                let world_landmarks: HandLandmarks = value
-                    .multi_hand_landmarks
-                    .get(0)
-                    .unwrap()
-                    .try_into()
-                    .unwrap();
-                let landmarks = v.try_into().unwrap();
+                let world_landmarks = HandLandmarks::from_json(
+                    value
+                        .multi_hand_landmarks
+                        .get(0)
+                        .ok_or(LandmarksError::NoKeyPoints)?,
+                    frame,
+                )?;
+                let landmarks = HandLandmarks::from_json(v, frame)?;
                 // info!("Detected set left hand");
-                Some(VelocityKalman::new(HandKeyPoints {
+                let hand = HandKeyPoints {
                     landmarks,
                     world_landmarks,
                     handedness,
-                }).set_measurement_noise(100.0).clone())
+                };
+                Some(FilterChain::new(hand, config))
             }
             None => None,
         };
@@ -182,101 +231,140 @@ impl From<HandLandmarkerResultJson> for CurrentHands {
                         .and_then(|v| v.get(0))
                         .map_or(0.0, |v| v.score),
                 };
-                let world_landmarks: HandLandmarks = value
-                    .multi_hand_landmarks
-                    .get(1)
-                    .unwrap()
-                    .try_into()
-                    .unwrap();
-                let landmarks = v.try_into().unwrap();
-                Some(VelocityKalman::new(HandKeyPoints {
+                let world_landmarks = HandLandmarks::from_json(
+                    value
+                        .multi_hand_landmarks
+                        .get(1)
+                        .ok_or(LandmarksError::NoKeyPoints)?,
+                    frame,
+                )?;
+                let landmarks = HandLandmarks::from_json(v, frame)?;
+                let hand = HandKeyPoints {
                     landmarks,
                     world_landmarks,
                     handedness,
-                }).set_measurement_noise(10.0).clone())
+                };
+                Some(FilterChain::new(hand, config))
             }
             None => None,
         };
 
-        return CurrentHands {
+        Ok(CurrentHands {
             left_hand,
             right_hand,
-        };
+        })
+    }
+
+    /// Merges a newly detected frame into the filters of each hand.
+    pub fn apply(&mut self, new_hands: CurrentHands, dt: f32, config: &FilterConfig) {
+        if let Some(new_left) = new_hands.left_hand {
+            if let Some(existing_left) = self.left_hand.as_mut() {
+                existing_left.configure(config);
+                existing_left.update(new_left.get(), dt);
+            } else {
+                self.left_hand = Some(new_left);
+            }
+        }
+        if let Some(new_right) = new_hands.right_hand {
+            if let Some(existing_right) = self.right_hand.as_mut() {
+                existing_right.configure(config);
+                existing_right.update(new_right.get(), dt);
+            } else {
+                self.right_hand = Some(new_right);
+            }
+        }
     }
 }
 
 #[derive(Resource, Default, Clone, Debug)]
-pub struct LastHandsUpdateTime(pub Option<Instant>);
+pub struct LastHandsUpdateTime(pub FrameClock);
 
 #[hot]
 fn set_hands_hot(payload: HandLandmarkerResult) -> impl IntoResponse {
-    match AsyncWorld
-        .resource::<GuiState>()
-        .get(|state| state.update_hands_data)
-    {
-        Ok(v) => {
+    match ingest_hands(payload) {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => ingest_error(err),
+    }
+}
+
+/// Feeds a hands frame into [`CurrentHands`], shared by the HTTP route and the stream socket.
+#[hot]
+pub fn ingest_hands(payload: HandLandmarkerResult) -> Result<(), IngestError> {
+    let (filter_config, frame) = match AsyncWorld.resource::<GuiState>().get(|state| {
+        (
+            state.update_hands_data,
+            state.hands_filter_config(),
+            state.mediapipe_frame(),
+        )
+    }) {
+        Ok((v, filter_config, frame)) => {
             if !v {
                 // info!("Not updating pose data");
-                return StatusCode::OK.into_response();
+                return Ok(());
             }
+            (filter_config, frame)
         }
         Err(err) => {
             let message = format!("Error accessing CurrentPose: {}", err);
             tracing::error!(message);
-            return internal_error(&message);
+            return Err(message.into());
+        }
+    };
+
+    if !live_input_active()? {
+        return Ok(());
+    }
+
+    let recorded = clone_if_recording(&payload);
+
+    let timestamp = payload.timestamp;
+
+    let new_hands = match CurrentHands::from_json(
+        payload.hand_landmarker_result,
+        &frame,
+        &filter_config,
+    ) {
+        Ok(hands) => hands,
+        Err(err) => {
+            let message = format!("Error converting HandLandmarkerResult to CurrentHands: {}", err);
+            tracing::error!(message);
+            return Err(message.into());
         }
     };
 
     let now = Instant::now();
 
-    // Retrieve and update the last update time
+    // Retrieve and update the last update time, rejecting frames older than the last one
     let dt = match AsyncWorld.resource::<LastHandsUpdateTime>().get_mut(
         |last_update_time: &mut LastHandsUpdateTime| {
-            let dt = last_update_time
-                .0
-                .map(|last| now.duration_since(last).as_secs_f32())
-                .unwrap_or(0.0);
-            last_update_time.0 = Some(now);
-            dt
+            last_update_time.0.tick(timestamp, now)
         },
     ) {
-        Ok(dt) => dt,
+        Ok(dt) => dt?,
         Err(err) => {
             let message = format!("Error accessing LastHandsUpdateTime: {}", err);
             tracing::error!(message);
-            return internal_error(&message);
+            return Err(message.into());
         }
     };
 
     match AsyncWorld
         .resource::<CurrentHands>()
-        .get_mut(|hands: &mut CurrentHands| {
-            let new_hands: CurrentHands = payload.hand_landmarker_result.into();
-
-            if let Some(new_left) = new_hands.left_hand {
-                if let Some(existing_left) = hands.left_hand.as_mut() {
-                    existing_left.update(new_left.get(), dt);
-                } else {
-                    hands.left_hand = Some(new_left); // fixed
-                }
-            }
-            if let Some(new_right) = new_hands.right_hand {
-                if let Some(existing_right) = hands.right_hand.as_mut() {
-                    existing_right.update(new_right.get(), dt);
-                } else {
-                    hands.right_hand = Some(new_right); // fixed
-                }
-            }
-        }) {
+        .get_mut(|hands: &mut CurrentHands| hands.apply(new_hands, dt, &filter_config))
+    {
         Ok(_) => {}
         Err(err) => {
             let message = format!("Error accessing CurrentPose: {}", err);
             tracing::error!(message);
-            return internal_error(&message).into_response();
+            return Err(message.into());
         }
     }
 
-    return StatusCode::OK.into_response();
+    if let Some(raw) = recorded {
+        record_frame(StreamMessage::Hands(raw));
+    }
+
+    return Ok(());
 }
 
 pub async fn get_hands() -> impl IntoResponse {
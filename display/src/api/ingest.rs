@@ -0,0 +1,141 @@
+use std::time::Instant;
+
+use bevy::prelude::*;
+use bevy_defer::{AsyncAccess, AsyncWorld};
+
+/// Time step used for the first frame of a stream, before there is anything to measure against.
+pub const DEFAULT_FRAME_DT: f32 = 1.0 / 30.0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum IngestError {
+    #[error(
+        "Stale frame: captured at {timestamp}ms, but a frame from {last}ms was already applied"
+    )]
+    StaleFrame { timestamp: f64, last: f64 },
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl From<String> for IngestError {
+    fn from(message: String) -> Self {
+        IngestError::Internal(message)
+    }
+}
+
+/// Tracks the timing of one input stream to work out the filter `dt` between frames.
+///
+/// Capture timestamps (in milliseconds) sent by the client are preferred, so network jitter
+/// does not leak into the filters. Frames without one fall back to the server arrival time.
+#[derive(Debug, Clone, Default)]
+pub struct FrameClock {
+    pub last_capture_ms: Option<f64>,
+    pub last_arrival: Option<Instant>,
+}
+
+impl FrameClock {
+    /// Returns the time in seconds since the previous frame, or an error if the frame was
+    /// captured before (or at the same time as) the last accepted one.
+    pub fn tick(&mut self, capture_ms: Option<f64>, now: Instant) -> Result<f32, IngestError> {
+        let dt = match (capture_ms, self.last_capture_ms) {
+            (Some(timestamp), Some(last)) => {
+                if timestamp <= last {
+                    return Err(IngestError::StaleFrame { timestamp, last });
+                }
+                ((timestamp - last) / 1000.0) as f32
+            }
+            _ => self
+                .last_arrival
+                .map(|last| now.duration_since(last).as_secs_f32())
+                .unwrap_or(DEFAULT_FRAME_DT),
+        };
+
+        if capture_ms.is_some() {
+            self.last_capture_ms = capture_ms;
+        }
+        self.last_arrival = Some(now);
+
+        Ok(dt)
+    }
+}
+
+/// Where `CurrentPose`, `CurrentHands` and `CurrentFace` (or the bones directly) are fed from.
+///
+/// Frames from the HTTP routes and the stream socket are only applied while this is
+/// [`ActiveSource::Live`].
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+#[reflect(Resource)]
+pub enum ActiveSource {
+    #[default]
+    Live,
+    Playback,
+    /// A BVH clip drives the bones directly, bypassing `CurrentPose` and `CurrentHands`.
+    Bvh,
+}
+
+/// Run condition for the systems turning `CurrentPose`/`CurrentHands` into bone rotations.
+pub fn landmarks_drive_body(source: Res<ActiveSource>) -> bool {
+    *source != ActiveSource::Bvh
+}
+
+/// Whether live frames should be applied, see [`ActiveSource`].
+pub fn live_input_active() -> Result<bool, IngestError> {
+    AsyncWorld
+        .resource::<ActiveSource>()
+        .get(|source| *source == ActiveSource::Live)
+        .map_err(|err| format!("Error accessing ActiveSource: {}", err).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn first_frame_uses_default_dt() {
+        let mut clock = FrameClock::default();
+        assert_eq!(
+            clock.tick(Some(1000.0), Instant::now()).unwrap(),
+            DEFAULT_FRAME_DT
+        );
+        let mut clock = FrameClock::default();
+        assert_eq!(clock.tick(None, Instant::now()).unwrap(), DEFAULT_FRAME_DT);
+    }
+
+    #[test]
+    fn capture_timestamps_give_dt() {
+        let mut clock = FrameClock::default();
+        let now = Instant::now();
+        clock.tick(Some(1000.0), now).unwrap();
+        // The arrival time is ignored while both frames have a capture timestamp
+        let dt = clock
+            .tick(Some(1050.0), now + Duration::from_millis(500))
+            .unwrap();
+        assert!((dt - 0.05).abs() < 1e-6);
+    }
+
+    #[test]
+    fn stale_frames_are_rejected() {
+        let mut clock = FrameClock::default();
+        let now = Instant::now();
+        clock.tick(Some(1000.0), now).unwrap();
+        for timestamp in [1000.0, 900.0] {
+            assert!(matches!(
+                clock.tick(Some(timestamp), now),
+                Err(IngestError::StaleFrame { last, .. }) if last == 1000.0
+            ));
+        }
+        // A rejected frame doesn't move the clock
+        let dt = clock.tick(Some(1020.0), now).unwrap();
+        assert!((dt - 0.02).abs() < 1e-6);
+    }
+
+    #[test]
+    fn frames_without_timestamps_use_arrival_time() {
+        let mut clock = FrameClock::default();
+        let now = Instant::now();
+        clock.tick(None, now).unwrap();
+        let dt = clock.tick(None, now + Duration::from_millis(40)).unwrap();
+        assert!((dt - 0.04).abs() < 1e-6);
+    }
+}
@@ -1,4 +1,8 @@
 pub mod api_server;
 pub mod hands_api;
+pub mod ingest;
 pub mod pose_api;
-pub mod face_api;
\ No newline at end of file
+pub mod session_api;
+pub mod face_api;
+pub mod stream_api;
+pub mod take_api;
\ No newline at end of file
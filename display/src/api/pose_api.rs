@@ -1,6 +1,9 @@
 use std::time::Instant;
 
-use crate::math::kalman_filter::VelocityKalman;
+use crate::api::ingest::{FrameClock, IngestError, live_input_active};
+use crate::api::stream_api::StreamMessage;
+use crate::session::recorder::{clone_if_recording, record_frame};
+use crate::math::filter::{FilterChain, FilterConfig, SmoothingFilter};
 use crate::{character_control::pose::PoseData, ui::state::GuiState};
 use axum::{Json, http::StatusCode, response::IntoResponse};
 use bevy::{ecs::resource::Resource, log::tracing};
@@ -9,7 +12,7 @@ use bevy_defer::AsyncWorld;
 use bevy_simple_subsecond_system::hot;
 use serde::{Deserialize, Serialize};
 
-use super::api_server::internal_error;
+use super::api_server::{ingest_error, internal_error};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct LandmarkJson {
@@ -30,6 +33,9 @@ pub struct PoseLandmarkerResultJson {
 pub struct PoseDataJson {
     #[serde(rename = "poseLandmarkerResult")]
     pub pose_landmarker_result: PoseLandmarkerResultJson,
+    /// Capture time of the video frame in milliseconds, if the client provides one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<f64>,
 }
 
 impl Default for PoseDataJson {
@@ -39,13 +45,26 @@ impl Default for PoseDataJson {
                 landmarks: Vec::new(),
                 world_landmarks: Vec::new(),
             },
+            timestamp: None,
         }
     }
 }
 
 // Define a Bevy Resource to hold the current PoseData
 #[derive(Resource, Default, Clone, Debug, Serialize, Deserialize)] // Derive necessary traits
-pub struct CurrentPose(pub Option<VelocityKalman<PoseData>>);
+pub struct CurrentPose(pub Option<FilterChain<PoseData>>);
+
+impl CurrentPose {
+    /// Feeds a converted frame into the filter, starting a new one if there is none yet.
+    pub fn apply(&mut self, pose_data: PoseData, dt: f32, config: &FilterConfig) {
+        if let Some(pose) = &mut self.0 {
+            pose.configure(config);
+            pose.update(&pose_data, dt);
+        } else {
+            self.0 = Some(FilterChain::new(pose_data, config));
+        }
+    }
+}
 
 pub async fn get_pose() -> impl IntoResponse {
     get_pose_hot()
@@ -70,80 +89,85 @@ pub async fn set_pose(Json(payload): Json<PoseDataJson>) -> impl IntoResponse {
 }
 
 #[derive(Resource, Default, Clone, Debug)]
-pub struct LastPoseUpdateTime(pub Option<Instant>);
+pub struct LastPoseUpdateTime(pub FrameClock);
 
 #[hot]
 pub fn set_pose_hot(payload: PoseDataJson) -> impl IntoResponse {
-    match AsyncWorld
-        .resource::<GuiState>()
-        .get(|state| state.update_pose_data)
-    {
-        Ok(v) => {
+    match ingest_pose(payload) {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => ingest_error(err),
+    }
+}
+
+/// Feeds a pose frame into [`CurrentPose`], shared by the HTTP route and the stream socket.
+#[hot]
+pub fn ingest_pose(payload: PoseDataJson) -> Result<(), IngestError> {
+    let (filter_config, frame) = match AsyncWorld.resource::<GuiState>().get(|state| {
+        (
+            state.update_pose_data,
+            state.pose_filter_config(),
+            state.mediapipe_frame(),
+        )
+    }) {
+        Ok((v, filter_config, frame)) => {
             if !v {
-                return StatusCode::OK.into_response();
+                return Ok(());
             }
+            (filter_config, frame)
         }
         Err(err) => {
             let message = format!("Error accessing CurrentPose: {}", err);
             tracing::error!(message);
-            return internal_error(&message);
+            return Err(message.into());
         }
     };
 
+    if !live_input_active()? {
+        return Ok(());
+    }
+
+    let recorded = clone_if_recording(&payload);
+
+    let timestamp = payload.timestamp;
+
     // First, convert PoseDataJson to PoseData
-    let pose_data: PoseData = match payload.try_into() {
+    let pose_data = match PoseData::from_json(&payload, &frame) {
         Ok(data) => data,
         Err(err) => {
             let message = format!("Error converting PoseDataJson to PoseData: {}", err);
             tracing::error!(message);
-            return internal_error(&message); // Or a more specific client error like BadRequest
+            return Err(message.into()); // Or a more specific client error like BadRequest
         }
     };
 
     let now = Instant::now();
 
-    // Retrieve and update the last update time
+    // Retrieve and update the last update time, rejecting frames older than the last one
     let dt = match AsyncWorld.resource::<LastPoseUpdateTime>().get_mut(
-        |last_update_time: &mut LastPoseUpdateTime| {
-            let dt = last_update_time
-                .0
-                .map(|last| now.duration_since(last).as_secs_f32())
-                .unwrap_or(0.0);
-            last_update_time.0 = Some(now);
-            dt
-        },
+        |last_update_time: &mut LastPoseUpdateTime| last_update_time.0.tick(timestamp, now),
     ) {
-        Ok(dt) => dt,
+        Ok(dt) => dt?,
         Err(err) => {
             let message = format!("Error accessing LastPoseUpdateTime: {}", err);
             tracing::error!(message);
-            return internal_error(&message);
+            return Err(message.into());
         }
     };
 
     match AsyncWorld
         .resource::<CurrentPose>()
-        .get_mut(|current_pose: &mut CurrentPose| {
-            // Check if a VelocityKalman<PoseData> already exists
-            if let Some(kalman_pose) = &mut current_pose.0 {
-                // If it exists, update it.
-                // You'll need a 'dt' (delta time) for the update.
-                // This 'dt' usually comes from how often this endpoint is hit,
-                // or from a timestamp within your PoseDataJson.
-                // For simplicity, let's assume a fixed dt for now, or you'll need to pass it.
-                // For a real-world scenario, you'd calculate dt based on previous timestamp.
-                // let dt = 1.0 / 60.0; // Example: assuming 60 FPS update rate
-                kalman_pose.update(&pose_data, dt);
-            } else {
-                // If it doesn't exist, create a new one.
-                current_pose.0 = Some(VelocityKalman::new(pose_data));
+        .get_mut(|current_pose: &mut CurrentPose| current_pose.apply(pose_data, dt, &filter_config))
+    {
+        Ok(_) => {
+            if let Some(raw) = recorded {
+                record_frame(StreamMessage::Pose(raw));
             }
-        }) {
-        Ok(_) => StatusCode::OK.into_response(),
+            Ok(())
+        }
         Err(err) => {
             let message = format!("Error accessing CurrentPose: {}", err);
             tracing::error!(message);
-            internal_error(&message)
+            Err(message.into())
         }
     }
 }
@@ -0,0 +1,31 @@
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use bevy_defer::{AsyncAccess, AsyncWorld};
+use bevy_simple_subsecond_system::hot;
+use serde_json::json;
+
+use crate::ui::state::GuiState;
+
+use super::api_server::internal_error;
+
+pub async fn start_recording() -> impl IntoResponse {
+    set_recording_hot(true)
+}
+
+pub async fn stop_recording() -> impl IntoResponse {
+    set_recording_hot(false)
+}
+
+/// Flips the same toggle as the settings window, the recorder picks it up on the next frame.
+#[hot]
+fn set_recording_hot(record: bool) -> impl IntoResponse {
+    match AsyncWorld
+        .resource::<GuiState>()
+        .get_mut(|state: &mut GuiState| state.record_session = record)
+    {
+        Ok(()) => (StatusCode::OK, Json(json!({ "recording": record }))).into_response(),
+        Err(err) => {
+            let message = format!("Error accessing GuiState: {}", err);
+            internal_error(&message)
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_tungstenite::WebSocketStream;
+use async_tungstenite::tungstenite::Message;
+use async_tungstenite::tungstenite::handshake::derive_accept_key;
+use async_tungstenite::tungstenite::protocol::Role;
+use axum::extract::Request;
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use bevy::log::tracing;
+use bevy_defer::AsyncWorld;
+use futures_util::{SinkExt, StreamExt};
+use hyper::upgrade::Upgraded;
+use serde::{Deserialize, Serialize};
+
+use crate::api::api_server::internal_error;
+use crate::api::face_api::{FaceLandmarkerResult, ingest_face};
+use crate::api::hands_api::{HandLandmarkerResult, ingest_hands};
+use crate::api::pose_api::{PoseDataJson, ingest_pose};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamKind {
+    Pose,
+    Hands,
+    Face,
+}
+
+/// A single frame sent over the `/stream` socket, tagged by its `type` field.
+///
+/// The payload fields are the same as the bodies of `/set_pose`, `/set_hands` and `/set_face`,
+/// e.g. `{"type": "pose", "poseLandmarkerResult": {...}}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamMessage {
+    Pose(PoseDataJson),
+    Hands(HandLandmarkerResult),
+    Face(FaceLandmarkerResult),
+}
+
+impl StreamMessage {
+    pub fn kind(&self) -> StreamKind {
+        match self {
+            StreamMessage::Pose(_) => StreamKind::Pose,
+            StreamMessage::Hands(_) => StreamKind::Hands,
+            StreamMessage::Face(_) => StreamKind::Face,
+        }
+    }
+}
+
+/// Sent back on the socket once per received frame, in the order the frames arrived.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamReply {
+    Ack {
+        stream: StreamKind,
+    },
+    Error {
+        stream: Option<StreamKind>,
+        message: String,
+    },
+}
+
+/// Upgrades the request to a WebSocket by hand, axum's own `WebSocketUpgrade` needs a tokio
+/// runtime and the web server runs without one.
+pub async fn stream(mut request: Request) -> Response {
+    let headers = request.headers();
+    let is_upgrade = headers
+        .get(header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    let Some(key) = headers
+        .get(header::SEC_WEBSOCKET_KEY)
+        .filter(|_| is_upgrade)
+    else {
+        return (StatusCode::BAD_REQUEST, "Expected a WebSocket upgrade").into_response();
+    };
+    let accept = derive_accept_key(key.as_bytes());
+
+    // The socket is driven by bevy_defer, like the handlers, so ingesting frames can reach the
+    // world
+    let on_upgrade = hyper::upgrade::on(&mut request);
+    AsyncWorld
+        .spawn_any(async move {
+            match on_upgrade.await {
+                Ok(upgraded) => {
+                    let socket =
+                        WebSocketStream::from_raw_socket(UpgradedIo(upgraded), Role::Server, None)
+                            .await;
+                    handle_stream(socket).await;
+                }
+                Err(err) => tracing::warn!("Mocap stream upgrade failed: {}", err),
+            }
+        })
+        .detach();
+
+    let mut response = StatusCode::SWITCHING_PROTOCOLS.into_response();
+    let headers = response.headers_mut();
+    headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+    match HeaderValue::from_str(&accept) {
+        Ok(accept) => {
+            headers.insert(header::SEC_WEBSOCKET_ACCEPT, accept);
+            response
+        }
+        Err(err) => internal_error(&format!("Invalid WebSocket accept key: {}", err)),
+    }
+}
+
+async fn handle_stream(mut socket: WebSocketStream<UpgradedIo>) {
+    tracing::info!("Mocap stream connected");
+
+    while let Some(message) = socket.next().await {
+        let message = match message {
+            Ok(m) => m,
+            Err(err) => {
+                tracing::warn!("Mocap stream receive error: {}", err);
+                break;
+            }
+        };
+
+        let reply = match message {
+            Message::Text(text) => handle_stream_message(serde_json::from_str(text.as_str())),
+            Message::Binary(bytes) => handle_stream_message(serde_json::from_slice(&bytes)),
+            Message::Close(_) => break,
+            // Pings are answered by tungstenite itself.
+            _ => continue,
+        };
+
+        let reply = match serde_json::to_string(&reply) {
+            Ok(r) => r,
+            Err(err) => {
+                tracing::error!("Failed to serialize stream reply: {}", err);
+                continue;
+            }
+        };
+
+        if socket.send(Message::Text(reply.into())).await.is_err() {
+            break;
+        }
+    }
+
+    tracing::info!("Mocap stream disconnected");
+}
+
+/// The upgraded connection as `futures` IO, which async-tungstenite reads and writes.
+struct UpgradedIo(Upgraded);
+
+impl futures_io::AsyncRead for UpgradedIo {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut buf = hyper::rt::ReadBuf::new(buf);
+        match hyper::rt::Read::poll_read(Pin::new(&mut self.0), cx, buf.unfilled()) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(buf.filled().len())),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl futures_io::AsyncWrite for UpgradedIo {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        hyper::rt::Write::poll_write(Pin::new(&mut self.0), cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        hyper::rt::Write::poll_flush(Pin::new(&mut self.0), cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        hyper::rt::Write::poll_shutdown(Pin::new(&mut self.0), cx)
+    }
+}
+
+fn handle_stream_message(message: serde_json::Result<StreamMessage>) -> StreamReply {
+    let message = match message {
+        Ok(m) => m,
+        Err(err) => {
+            return StreamReply::Error {
+                stream: None,
+                message: format!("Invalid stream message: {}", err),
+            };
+        }
+    };
+
+    let stream = message.kind();
+    let result = match message {
+        StreamMessage::Pose(payload) => ingest_pose(payload),
+        StreamMessage::Hands(payload) => ingest_hands(payload),
+        StreamMessage::Face(payload) => ingest_face(payload),
+    };
+
+    match result {
+        Ok(()) => StreamReply::Ack { stream },
+        Err(err) => StreamReply::Error {
+            stream: Some(stream),
+            message: err.to_string(),
+        },
+    }
+}
@@ -0,0 +1,32 @@
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use bevy_defer::{AsyncAccess, AsyncWorld};
+use bevy_simple_subsecond_system::hot;
+use serde_json::json;
+
+use crate::ui::state::GuiState;
+
+use super::api_server::internal_error;
+
+pub async fn start_take() -> impl IntoResponse {
+    set_take_hot(true)
+}
+
+pub async fn stop_take() -> impl IntoResponse {
+    set_take_hot(false)
+}
+
+/// Flips the same toggle as the settings window. Stopping a take exports it in the formats
+/// enabled there.
+#[hot]
+fn set_take_hot(record: bool) -> impl IntoResponse {
+    match AsyncWorld
+        .resource::<GuiState>()
+        .get_mut(|state: &mut GuiState| state.record_take = record)
+    {
+        Ok(()) => (StatusCode::OK, Json(json!({ "recording_take": record }))).into_response(),
+        Err(err) => {
+            let message = format!("Error accessing GuiState: {}", err);
+            internal_error(&message)
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Biovision Hierarchy (`.bvh`) motion files.
+
+use bevy::prelude::*;
+use strum_macros::{Display, EnumString};
+
+pub mod names;
+pub mod parser;
+pub mod player;
+pub mod writer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+pub enum BvhChannel {
+    Xposition,
+    Yposition,
+    Zposition,
+    Xrotation,
+    Yrotation,
+    Zrotation,
+}
+
+#[derive(Debug, Clone)]
+pub struct BvhJoint {
+    pub name: String,
+    pub parent: Option<usize>,
+    /// Position relative to the parent joint when every rotation is zero.
+    pub offset: Vec3,
+    pub channels: Vec<BvhChannel>,
+    /// Offset of the `End Site` of a joint without children.
+    pub end_site: Option<Vec3>,
+}
+
+/// A BVH skeleton and its motion. Joints are stored with parents before their children.
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    pub joints: Vec<BvhJoint>,
+    /// Seconds between two frames.
+    pub frame_time: f32,
+    /// The channel values of every joint in order, one entry per frame.
+    pub frames: Vec<Vec<f32>>,
+}
+
+impl Bvh {
+    pub fn children(&self, joint: usize) -> impl Iterator<Item = usize> + '_ {
+        self.joints
+            .iter()
+            .enumerate()
+            .filter(move |(_, j)| j.parent == Some(joint))
+            .map(|(i, _)| i)
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.frames.len().saturating_sub(1) as f32 * self.frame_time
+    }
+
+    /// Index of the first value of every joint within a frame.
+    pub fn channel_offsets(&self) -> Vec<usize> {
+        self.joints
+            .iter()
+            .scan(0, |offset, joint| {
+                let start = *offset;
+                *offset += joint.channels.len();
+                Some(start)
+            })
+            .collect()
+    }
+
+    /// The position channels of `joint` and its rotation, composed in channel order.
+    ///
+    /// `offset` is the index of the joint's first value, see [`Bvh::channel_offsets`].
+    pub fn joint_pose(&self, frame: &[f32], joint: usize, offset: usize) -> (Vec3, Quat) {
+        let mut position = Vec3::ZERO;
+        let mut rotation = Quat::IDENTITY;
+        for (i, channel) in self.joints[joint].channels.iter().enumerate() {
+            let value = frame.get(offset + i).copied().unwrap_or_default();
+            match channel {
+                BvhChannel::Xposition => position.x = value,
+                BvhChannel::Yposition => position.y = value,
+                BvhChannel::Zposition => position.z = value,
+                BvhChannel::Xrotation => rotation *= Quat::from_rotation_x(value.to_radians()),
+                BvhChannel::Yrotation => rotation *= Quat::from_rotation_y(value.to_radians()),
+                BvhChannel::Zrotation => rotation *= Quat::from_rotation_z(value.to_radians()),
+            }
+        }
+        (position, rotation)
+    }
+}
@@ -0,0 +1,53 @@
+use std::fs;
+use std::str::FromStr;
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::character_control::humanoid::HumanoidBone;
+use crate::model_plugin::asset_file;
+
+/// Relative to the `assets` directory.
+pub const NAME_TABLE_PATH: &str = "bvh/name_table.ron";
+
+/// Maps BVH joint names onto [`HumanoidBone`]s, see `assets/bvh/name_table.ron`.
+///
+/// Joints that are not listed are matched by their humanoid name (`"LeftUpperArm"`), and are
+/// otherwise only used to carry the rotation down to their children.
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct BvhNameTable {
+    /// Multiplies root positions, e.g. `0.01` for clips authored in centimetres.
+    #[serde(default = "default_position_scale")]
+    pub position_scale: f32,
+    pub joints: HashMap<String, HumanoidBone>,
+}
+
+fn default_position_scale() -> f32 {
+    1.0
+}
+
+impl Default for BvhNameTable {
+    fn default() -> Self {
+        Self {
+            position_scale: default_position_scale(),
+            joints: HashMap::new(),
+        }
+    }
+}
+
+impl BvhNameTable {
+    pub fn load() -> Result<Self, String> {
+        let path = asset_file(NAME_TABLE_PATH);
+        let content = fs::read_to_string(&path)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        ron::from_str(&content).map_err(|err| format!("Invalid {}: {}", path.display(), err))
+    }
+
+    pub fn bone(&self, joint: &str) -> Option<HumanoidBone> {
+        self.joints
+            .get(joint)
+            .copied()
+            .or_else(|| HumanoidBone::from_str(joint).ok())
+    }
+}
@@ -0,0 +1,248 @@
+use std::str::FromStr;
+
+use bevy::prelude::*;
+
+use crate::bvh::{Bvh, BvhChannel, BvhJoint};
+
+#[derive(Debug, thiserror::Error)]
+pub enum BvhParseError {
+    #[error("Unexpected end of file")]
+    UnexpectedEnd,
+    #[error("Expected '{expected}', found '{found}'")]
+    Unexpected { expected: String, found: String },
+    #[error("Invalid number '{0}'")]
+    InvalidNumber(String),
+    #[error("Unknown channel '{0}'")]
+    UnknownChannel(String),
+    #[error("Frame {frame} has {found} values, expected {expected}")]
+    FrameLength {
+        frame: usize,
+        found: usize,
+        expected: usize,
+    },
+}
+
+pub fn parse_bvh(source: &str) -> Result<Bvh, BvhParseError> {
+    let mut tokens = Tokens(source.split_whitespace());
+    let mut bvh = Bvh::default();
+
+    tokens.expect("HIERARCHY")?;
+    loop {
+        match tokens.next()? {
+            "ROOT" => parse_joint(&mut tokens, &mut bvh, None)?,
+            "MOTION" => break,
+            found => {
+                return Err(BvhParseError::Unexpected {
+                    expected: "ROOT".to_string(),
+                    found: found.to_string(),
+                });
+            }
+        }
+    }
+
+    tokens.expect("Frames:")?;
+    let frame_count = tokens.number::<usize>()?;
+    tokens.expect("Frame")?;
+    tokens.expect("Time:")?;
+    bvh.frame_time = tokens.number()?;
+
+    let expected: usize = bvh.joints.iter().map(|j| j.channels.len()).sum();
+    let values = tokens
+        .0
+        .map(|t| {
+            t.parse::<f32>()
+                .map_err(|_| BvhParseError::InvalidNumber(t.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if expected > 0 {
+        bvh.frames = values.chunks(expected).map(<[f32]>::to_vec).collect();
+    }
+    // Some exporters write a wrong frame count, trust the data but reject cut off frames.
+    if let Some(last) = bvh.frames.last() {
+        if last.len() != expected {
+            return Err(BvhParseError::FrameLength {
+                frame: bvh.frames.len() - 1,
+                found: last.len(),
+                expected,
+            });
+        }
+    }
+    if bvh.frames.len() != frame_count {
+        warn!(
+            "BVH declares {} frames but contains {}",
+            frame_count,
+            bvh.frames.len()
+        );
+    }
+
+    Ok(bvh)
+}
+
+/// Parses a joint after its `ROOT`/`JOINT` keyword, including all of its children.
+fn parse_joint(
+    tokens: &mut Tokens,
+    bvh: &mut Bvh,
+    parent: Option<usize>,
+) -> Result<(), BvhParseError> {
+    let index = bvh.joints.len();
+    bvh.joints.push(BvhJoint {
+        name: tokens.next()?.to_string(),
+        parent,
+        offset: Vec3::ZERO,
+        channels: Vec::new(),
+        end_site: None,
+    });
+
+    tokens.expect("{")?;
+    loop {
+        match tokens.next()? {
+            "OFFSET" => bvh.joints[index].offset = tokens.vec3()?,
+            "CHANNELS" => {
+                let count = tokens.number::<usize>()?;
+                for _ in 0..count {
+                    let name = tokens.next()?;
+                    let channel = BvhChannel::from_str(name)
+                        .map_err(|_| BvhParseError::UnknownChannel(name.to_string()))?;
+                    bvh.joints[index].channels.push(channel);
+                }
+            }
+            "JOINT" => parse_joint(tokens, bvh, Some(index))?,
+            "End" => {
+                tokens.expect("Site")?;
+                tokens.expect("{")?;
+                tokens.expect("OFFSET")?;
+                bvh.joints[index].end_site = Some(tokens.vec3()?);
+                tokens.expect("}")?;
+            }
+            "}" => return Ok(()),
+            found => {
+                return Err(BvhParseError::Unexpected {
+                    expected: "}".to_string(),
+                    found: found.to_string(),
+                });
+            }
+        }
+    }
+}
+
+struct Tokens<'a>(std::str::SplitWhitespace<'a>);
+
+impl<'a> Tokens<'a> {
+    fn next(&mut self) -> Result<&'a str, BvhParseError> {
+        self.0.next().ok_or(BvhParseError::UnexpectedEnd)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), BvhParseError> {
+        let found = self.next()?;
+        if found != expected {
+            return Err(BvhParseError::Unexpected {
+                expected: expected.to_string(),
+                found: found.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, BvhParseError> {
+        let token = self.next()?;
+        token
+            .parse()
+            .map_err(|_| BvhParseError::InvalidNumber(token.to_string()))
+    }
+
+    fn vec3(&mut self) -> Result<Vec3, BvhParseError> {
+        Ok(Vec3::new(self.number()?, self.number()?, self.number()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bvh::writer::write_bvh;
+
+    fn joint(name: &str, parent: Option<usize>, offset: Vec3, channels: &[BvhChannel]) -> BvhJoint {
+        BvhJoint {
+            name: name.to_string(),
+            parent,
+            offset,
+            channels: channels.to_vec(),
+            end_site: None,
+        }
+    }
+
+    fn bvh() -> Bvh {
+        use BvhChannel::*;
+
+        let rotation = [Zrotation, Xrotation, Yrotation];
+        let mut joints = vec![
+            joint(
+                "Hips",
+                None,
+                Vec3::ZERO,
+                &[
+                    Xposition, Yposition, Zposition, Zrotation, Xrotation, Yrotation,
+                ],
+            ),
+            joint("Spine", Some(0), Vec3::new(0.0, 10.0, 0.0), &rotation),
+            joint("Head", Some(1), Vec3::new(0.0, 20.0, 1.5), &rotation),
+            joint("LeftUpLeg", Some(0), Vec3::new(8.0, -2.0, 0.0), &rotation),
+        ];
+        joints[2].end_site = Some(Vec3::new(0.0, 5.0, 0.0));
+        joints[3].end_site = Some(Vec3::new(0.0, -40.0, 0.0));
+
+        Bvh {
+            joints,
+            frame_time: 1.0 / 30.0,
+            frames: vec![
+                (0..15).map(|v| v as f32).collect(),
+                (0..15).map(|v| v as f32 * -0.5 + 0.25).collect(),
+            ],
+        }
+    }
+
+    fn write(bvh: &Bvh) -> String {
+        let mut out = Vec::new();
+        write_bvh(bvh, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn written_files_parse_back() {
+        let bvh = bvh();
+        let parsed = parse_bvh(&write(&bvh)).unwrap();
+
+        assert_eq!(parsed.joints.len(), bvh.joints.len());
+        for (parsed, joint) in parsed.joints.iter().zip(&bvh.joints) {
+            assert_eq!(parsed.name, joint.name);
+            assert_eq!(parsed.parent, joint.parent);
+            assert_eq!(parsed.offset, joint.offset);
+            assert_eq!(parsed.channels, joint.channels);
+            assert_eq!(parsed.end_site, joint.end_site);
+        }
+        assert!((parsed.frame_time - bvh.frame_time).abs() < 1e-6);
+        assert_eq!(parsed.frames, bvh.frames);
+    }
+
+    #[test]
+    fn cut_off_frames_are_rejected() {
+        let source = write(&bvh());
+        let cut = source.trim_end().rsplit_once(' ').unwrap().0;
+        assert!(matches!(
+            parse_bvh(cut),
+            Err(BvhParseError::FrameLength {
+                frame: 1,
+                found: 14,
+                expected: 15
+            })
+        ));
+    }
+
+    #[test]
+    fn unknown_channels_are_errors() {
+        let source = write(&bvh()).replacen("Zrotation", "Wrotation", 1);
+        assert!(matches!(
+            parse_bvh(&source),
+            Err(BvhParseError::UnknownChannel(channel)) if channel == "Wrotation"
+        ));
+    }
+}
@@ -0,0 +1,254 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::prelude::*;
+
+use crate::api::ingest::ActiveSource;
+use crate::bvh::Bvh;
+use crate::bvh::names::BvhNameTable;
+use crate::bvh::parser::parse_bvh;
+use crate::character_control::character_controller::CharacterParts;
+use crate::character_control::humanoid::RestPose;
+use crate::character_control::joint_limits::JointLimits;
+use crate::ui::state::GuiState;
+
+/// Drives the avatar from a BVH clip, in place of the MediaPipe driven [`rotate_body`] and
+/// [`rotate_hands`].
+///
+/// [`rotate_body`]: crate::character_control::rotate_body::rotate_body
+/// [`rotate_hands`]: crate::character_control::rotate_hands::rotate_hands
+///
+/// Clips are picked in the timeline from the recordings directory, see
+/// [`recordings_dir`](crate::session::recordings_dir), or played from startup with `clip`.
+#[derive(Default)]
+pub struct BvhPlayerPlugin {
+    /// A clip anywhere on disk, relative to the working directory.
+    pub clip: Option<PathBuf>,
+}
+
+impl Plugin for BvhPlayerPlugin {
+    fn build(&self, app: &mut App) {
+        let name_table = BvhNameTable::load().unwrap_or_else(|err| {
+            warn!("{}, only humanoid joint names will be matched", err);
+            BvhNameTable::default()
+        });
+
+        let mut player = BvhPlayer::default();
+        if let Some(path) = &self.clip {
+            match player.load(path) {
+                Ok(frames) => {
+                    info!("Loaded {} frames from {}", frames, path.display());
+                    player.playing = true;
+                }
+                Err(err) => error!("{}", err),
+            }
+        }
+
+        app.insert_resource(name_table)
+            .insert_resource(player)
+            .add_systems(Update, apply_bvh);
+    }
+}
+
+#[derive(Resource)]
+pub struct BvhPlayer {
+    clip: Option<Bvh>,
+    /// Playback position in seconds.
+    position: f64,
+    pub playing: bool,
+    pub looping: bool,
+    pub speed: f32,
+}
+
+impl Default for BvhPlayer {
+    fn default() -> Self {
+        Self {
+            clip: None,
+            position: 0.0,
+            playing: false,
+            looping: true,
+            speed: 1.0,
+        }
+    }
+}
+
+impl BvhPlayer {
+    /// Loads a BVH clip and rewinds to its start, returning the number of frames.
+    pub fn load(&mut self, path: &Path) -> Result<usize, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        let clip = parse_bvh(&content).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+        let frames = clip.frames.len();
+        self.clip = Some(clip);
+        self.position = 0.0;
+        Ok(frames)
+    }
+
+    pub fn unload(&mut self) {
+        *self = Self {
+            looping: self.looping,
+            speed: self.speed,
+            ..Self::default()
+        };
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.clip.is_some()
+    }
+
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.clip
+            .as_ref()
+            .map(|c| c.duration() as f64)
+            .unwrap_or(0.0)
+    }
+
+    pub fn seek(&mut self, position: f64) {
+        self.position = position.clamp(0.0, self.duration());
+    }
+
+    fn advance(&mut self, delta: f64) {
+        let duration = self.duration();
+        self.position += delta * self.speed as f64;
+        if self.position > duration {
+            if self.looping && duration > 0.0 {
+                self.position %= duration;
+            } else {
+                self.position = duration;
+                self.playing = false;
+            }
+        }
+    }
+
+    fn current_frame(&self) -> Option<(&Bvh, &[f32])> {
+        let clip = self.clip.as_ref()?;
+        let index = if clip.frame_time > 0.0 {
+            (self.position / clip.frame_time as f64) as usize
+        } else {
+            0
+        };
+        let frame = clip
+            .frames
+            .get(index.min(clip.frames.len().checked_sub(1)?))?;
+        Some((clip, frame))
+    }
+}
+
+#[hot]
+fn apply_bvh(
+    time: Res<Time>,
+    gui_state: Res<GuiState>,
+    name_table: Res<BvhNameTable>,
+    parts: Res<CharacterParts>,
+    rest_pose: Res<RestPose>,
+    joint_limits: Res<JointLimits>,
+    mut player: ResMut<BvhPlayer>,
+    mut active_source: ResMut<ActiveSource>,
+    child_of_q: Query<&ChildOf>,
+    mut transform_q: Query<&mut Transform>,
+) {
+    if !player.is_loaded() {
+        if *active_source == ActiveSource::Bvh {
+            *active_source = ActiveSource::Live;
+        }
+        return;
+    }
+    if *active_source != ActiveSource::Bvh {
+        *active_source = ActiveSource::Bvh;
+    }
+
+    if player.playing {
+        player.advance(time.delta_secs_f64());
+    }
+    let Some((clip, frame)) = player.current_frame() else {
+        return;
+    };
+
+    // Global joint rotations of the clip, relative to its T-posed rest.
+    let offsets = clip.channel_offsets();
+    let mut globals: Vec<Quat> = Vec::with_capacity(clip.joints.len());
+    let mut desired: HashMap<Entity, Quat> = HashMap::new();
+    let mut root_position = None;
+    for (i, joint) in clip.joints.iter().enumerate() {
+        let (position, rotation) = clip.joint_pose(frame, i, offsets[i]);
+        let parent = joint.parent.map(|p| globals[p]).unwrap_or(Quat::IDENTITY);
+        globals.push(parent * rotation);
+
+        let Some(entity) = name_table
+            .bone(&joint.name)
+            .and_then(|bone| parts.bone(bone))
+        else {
+            continue;
+        };
+        if joint.parent.is_none() {
+            // Relative to the first frame, so clips start where the avatar stands.
+            let (start, _) = clip.joint_pose(&clip.frames[0], i, offsets[i]);
+            root_position = Some(position - start);
+        }
+        let rest = chain_from_root(entity, parts.root, &child_of_q)
+            .into_iter()
+            .fold(Quat::IDENTITY, |acc, e| acc * rest_pose.rotation(e));
+        desired.insert(entity, globals[i] * rest);
+    }
+
+    for (&entity, &global) in desired.iter() {
+        // The current global rotation of the parent, with this frame's targets applied.
+        let mut chain = chain_from_root(entity, parts.root, &child_of_q);
+        chain.pop();
+        let parent_global = chain.into_iter().fold(Quat::IDENTITY, |acc, e| {
+            desired.get(&e).copied().unwrap_or_else(|| {
+                acc * transform_q
+                    .get(e)
+                    .map(|t| t.rotation)
+                    .unwrap_or(Quat::IDENTITY)
+            })
+        });
+
+        if let Ok(mut transform) = transform_q.get_mut(entity) {
+            // Clips made for other skeletons can turn joints further than the model allows
+            transform.rotation = joint_limits.apply(
+                entity,
+                parent_global.inverse() * global,
+                rest_pose.rotation(entity),
+            );
+        }
+    }
+
+    if let (Some(root), Some(position)) = (parts.root, root_position) {
+        if let Ok(mut transform) = transform_q.get_mut(root) {
+            let rest = rest_pose.0.get(&root).map(|t| t.translation);
+            let offset = if gui_state.move_root {
+                position * name_table.position_scale * gui_state.move_scale
+            } else {
+                Vec3::ZERO
+            };
+            transform.translation = rest.unwrap_or_default() + offset;
+        }
+    }
+}
+
+/// Entities from the avatar root down to `entity`, both included.
+fn chain_from_root(
+    entity: Entity,
+    root: Option<Entity>,
+    child_of_q: &Query<&ChildOf>,
+) -> Vec<Entity> {
+    let mut chain = vec![entity];
+    let mut current = entity;
+    while Some(current) != root {
+        let Ok(child_of) = child_of_q.get(current) else {
+            break;
+        };
+        current = child_of.parent();
+        chain.push(current);
+    }
+    chain.reverse();
+    chain
+}
@@ -0,0 +1,69 @@
+use std::io::{self, Write};
+
+use bevy::prelude::*;
+
+use crate::bvh::Bvh;
+
+pub fn write_bvh(bvh: &Bvh, mut out: impl Write) -> io::Result<()> {
+    writeln!(out, "HIERARCHY")?;
+    for (i, joint) in bvh.joints.iter().enumerate() {
+        if joint.parent.is_none() {
+            write_joint(bvh, i, 0, &mut out)?;
+        }
+    }
+
+    writeln!(out, "MOTION")?;
+    writeln!(out, "Frames: {}", bvh.frames.len())?;
+    writeln!(out, "Frame Time: {:.6}", bvh.frame_time)?;
+    for frame in &bvh.frames {
+        let values: Vec<String> = frame.iter().map(|v| format!("{v:.6}")).collect();
+        writeln!(out, "{}", values.join(" "))?;
+    }
+    Ok(())
+}
+
+fn write_joint(bvh: &Bvh, index: usize, depth: usize, out: &mut impl Write) -> io::Result<()> {
+    let joint = &bvh.joints[index];
+    let indent = "\t".repeat(depth);
+    let keyword = if joint.parent.is_none() {
+        "ROOT"
+    } else {
+        "JOINT"
+    };
+
+    writeln!(out, "{indent}{keyword} {}", joint.name)?;
+    writeln!(out, "{indent}{{")?;
+    write_offset(joint.offset, depth + 1, out)?;
+    let channels: Vec<String> = joint.channels.iter().map(|c| c.to_string()).collect();
+    writeln!(
+        out,
+        "{indent}\tCHANNELS {} {}",
+        channels.len(),
+        channels.join(" ")
+    )?;
+
+    let mut has_children = false;
+    for child in bvh.children(index) {
+        has_children = true;
+        write_joint(bvh, child, depth + 1, out)?;
+    }
+    if !has_children {
+        writeln!(out, "{indent}\tEnd Site")?;
+        writeln!(out, "{indent}\t{{")?;
+        write_offset(joint.end_site.unwrap_or(Vec3::ZERO), depth + 2, out)?;
+        writeln!(out, "{indent}\t}}")?;
+    }
+
+    writeln!(out, "{indent}}}")
+}
+
+fn write_offset(offset: Vec3, depth: usize, out: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "{}OFFSET {:.6} {:.6} {:.6}",
+        "\t".repeat(depth),
+        offset.x,
+        offset.y,
+        offset.z
+    )
+}
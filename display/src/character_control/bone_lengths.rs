@@ -0,0 +1,224 @@
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::prelude::*;
+
+use crate::api::pose_api::CurrentPose;
+use crate::character_control::limb_ik::{LEFT_ARM, LEFT_LEG, Limb, RIGHT_ARM, RIGHT_LEG};
+use crate::character_control::pose::PoseLandmarkIndex::{self, *};
+use crate::character_control::pose::PoseLandmarks;
+use crate::math::filter::SmoothingFilter;
+use crate::math::two_bone_ik::solve_two_bone_ik;
+use crate::ui::state::GuiState;
+
+/// How long the user is sampled for while standing still.
+const CALIBRATION_SECONDS: f32 = 3.0;
+
+/// Segments with an end below this visibility are left out of the average.
+const MIN_VISIBILITY: f32 = 0.5;
+
+const SEGMENT_COUNT: usize = 8;
+
+/// The limb segments as `(parent, child)`, the upper and lower segment of each of [`LIMBS`].
+const SEGMENTS: [(PoseLandmarkIndex, PoseLandmarkIndex); SEGMENT_COUNT] = [
+    (LeftShoulder, LeftElbow),
+    (LeftElbow, LeftWrist),
+    (RightShoulder, RightElbow),
+    (RightElbow, RightWrist),
+    (LeftHip, LeftKnee),
+    (LeftKnee, LeftAnkle),
+    (RightHip, RightKnee),
+    (RightKnee, RightAnkle),
+];
+
+const LIMBS: [Limb; SEGMENT_COUNT / 2] = [LEFT_ARM, RIGHT_ARM, LEFT_LEG, RIGHT_LEG];
+
+/// Arm and leg segment lengths measured by a calibration pass, see
+/// [`GuiState::calibrate_bone_lengths`].
+#[derive(Resource, Default, Debug, Clone)]
+pub struct BoneLengths {
+    /// Average length in metres of each limb segment, once calibrated.
+    pub lengths: Option<[f32; SEGMENT_COUNT]>,
+    calibration: Option<Calibration>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Calibration {
+    elapsed: f32,
+    sums: [f32; SEGMENT_COUNT],
+    counts: [u32; SEGMENT_COUNT],
+}
+
+impl BoneLengths {
+    /// Refits every limb to its calibrated lengths, keeping the tracked wrist or ankle and
+    /// bending towards the tracked elbow or knee. The segments turn to make up for a badly
+    /// tracked middle joint, and ends out of reach are reached for with the limb straight.
+    /// Does nothing before a calibration.
+    pub fn constrain(&self, landmarks: &mut PoseLandmarks) {
+        let Some(lengths) = self.lengths else {
+            return;
+        };
+
+        for (limb, lengths) in LIMBS.iter().zip(lengths.chunks_exact(2)) {
+            let Some((mid, end)) = solve_two_bone_ik(
+                landmarks[limb.root].position,
+                landmarks[limb.end].position,
+                landmarks[limb.mid].position,
+                lengths[0],
+                lengths[1],
+            ) else {
+                continue;
+            };
+
+            let offset = end - landmarks[limb.end].position;
+            landmarks[limb.mid].position = mid;
+            landmarks[limb.end].position = end;
+            for &landmark in limb.carried {
+                landmarks[landmark].position += offset;
+            }
+        }
+    }
+
+    fn finish_calibration(&mut self) {
+        let Some(calibration) = self.calibration.take() else {
+            return;
+        };
+
+        if calibration.counts.contains(&0) {
+            warn!("Bone length calibration failed, not every limb was visible");
+            return;
+        }
+
+        let lengths: [f32; SEGMENT_COUNT] =
+            std::array::from_fn(|i| calibration.sums[i] / calibration.counts[i] as f32);
+        info!("Calibrated bone lengths: {:?}", lengths);
+        self.lengths = Some(lengths);
+    }
+}
+
+/// Averages the segment lengths of the filtered pose while [`GuiState::calibrate_bone_lengths`]
+/// is on, and turns it off once [`CALIBRATION_SECONDS`] have passed.
+#[hot]
+pub fn calibrate_bone_lengths(
+    mut gui_state: ResMut<GuiState>,
+    time: Res<Time>,
+    current_pose: Res<CurrentPose>,
+    mut bone_lengths: ResMut<BoneLengths>,
+) {
+    match (
+        gui_state.calibrate_bone_lengths,
+        bone_lengths.calibration.is_some(),
+    ) {
+        (true, false) => {
+            info!("Calibrating bone lengths, stand still");
+            bone_lengths.calibration = Some(Calibration::default());
+        }
+        (false, true) => {
+            // Stopped early, use what was measured so far
+            bone_lengths.finish_calibration();
+            return;
+        }
+        (false, false) => return,
+        (true, true) => {}
+    }
+
+    let Some(calibration) = bone_lengths.calibration.as_mut() else {
+        return;
+    };
+    calibration.elapsed += time.delta_secs();
+
+    if let Some(pose) = current_pose.0.as_ref() {
+        let landmarks = &pose.get().world_landmarks;
+        for (i, (parent, child)) in SEGMENTS.iter().enumerate() {
+            let (parent, child) = (landmarks[*parent], landmarks[*child]);
+            if parent.visibility < MIN_VISIBILITY || child.visibility < MIN_VISIBILITY {
+                continue;
+            }
+            calibration.sums[i] += parent.position.distance(child.position);
+            calibration.counts[i] += 1;
+        }
+    }
+
+    if calibration.elapsed >= CALIBRATION_SECONDS {
+        bone_lengths.finish_calibration();
+        gui_state.calibrate_bone_lengths = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::landmarks::Landmark;
+
+    fn landmarks(joints: &[(PoseLandmarkIndex, Vec3)]) -> PoseLandmarks {
+        let mut landmarks = PoseLandmarks::from_fn(|_| Landmark {
+            position: Vec3::ZERO,
+            visibility: 1.0,
+        });
+        for &(joint, position) in joints {
+            landmarks[joint].position = position;
+        }
+        landmarks
+    }
+
+    fn calibrated(upper: f32, lower: f32) -> BoneLengths {
+        BoneLengths {
+            lengths: Some(std::array::from_fn(
+                |i| if i % 2 == 0 { upper } else { lower },
+            )),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn limbs_are_refitted_to_their_lengths() {
+        // A bent arm tracked with a short forearm
+        let wrist = Vec3::new(0.4, -0.1, 0.0);
+        let mut landmarks = landmarks(&[
+            (LeftElbow, Vec3::new(0.3, 0.0, 0.0)),
+            (LeftWrist, wrist),
+            (LeftIndex, wrist + Vec3::X * 0.1),
+        ]);
+        let tracked = landmarks.clone();
+        calibrated(0.3, 0.3).constrain(&mut landmarks);
+
+        let (shoulder, elbow) = (
+            landmarks[LeftShoulder].position,
+            landmarks[LeftElbow].position,
+        );
+        assert!((shoulder.distance(elbow) - 0.3).abs() < 1e-5);
+        assert!((elbow.distance(wrist) - 0.3).abs() < 1e-5);
+        // The hand stays where it was tracked, the segments turn instead
+        assert_eq!(landmarks[LeftWrist].position, wrist);
+        assert_eq!(landmarks[LeftIndex].position, tracked[LeftIndex].position);
+        let upper = (elbow - shoulder).normalize();
+        assert!(upper.angle_between(Vec3::X) > 0.3);
+        // Still bent the way it was tracked
+        assert!(elbow.y > wrist.y);
+    }
+
+    #[test]
+    fn ends_out_of_reach_are_pulled_in() {
+        let mut landmarks = landmarks(&[
+            (RightHip, Vec3::ZERO),
+            (RightKnee, Vec3::new(0.0, -0.5, 0.05)),
+            (RightAnkle, Vec3::new(0.0, -1.0, 0.0)),
+            (RightHeel, Vec3::new(0.0, -1.05, -0.05)),
+        ]);
+        calibrated(0.4, 0.4).constrain(&mut landmarks);
+
+        let ankle = landmarks[RightAnkle].position;
+        assert!(ankle.abs_diff_eq(Vec3::new(0.0, -0.8, 0.0), 1e-5));
+        assert!(
+            landmarks[RightHeel]
+                .position
+                .abs_diff_eq(Vec3::new(0.0, -0.85, -0.05), 1e-5)
+        );
+    }
+
+    #[test]
+    fn nothing_changes_before_calibration() {
+        let mut landmarks = landmarks(&[(LeftElbow, Vec3::X), (LeftWrist, Vec3::Y)]);
+        let tracked = landmarks.clone();
+        BoneLengths::default().constrain(&mut landmarks);
+        assert_eq!(landmarks, tracked);
+    }
+}
@@ -0,0 +1,92 @@
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use crate::character_control::humanoid::HumanoidBone;
+use crate::character_control::joint_limits::JointLimit;
+use crate::model_plugin::asset_file;
+
+/// Bones the body can't be driven without, a model missing any of them is reported as an error.
+pub const REQUIRED_BONES: [HumanoidBone; 10] = [
+    HumanoidBone::Hips,
+    HumanoidBone::Neck,
+    HumanoidBone::LeftUpperArm,
+    HumanoidBone::LeftLowerArm,
+    HumanoidBone::RightUpperArm,
+    HumanoidBone::RightLowerArm,
+    HumanoidBone::LeftUpperLeg,
+    HumanoidBone::LeftLowerLeg,
+    HumanoidBone::RightUpperLeg,
+    HumanoidBone::RightLowerLeg,
+];
+
+/// Maps the [`CharacterParts`] of a model onto its glTF node names, loaded from a `.bones.ron`
+/// file next to the model, e.g. `assets/models/model1.bones.ron`, or from the humanoid of a VRM.
+///
+/// Bones that are not listed are looked up by their humanoid name (`"LeftUpperArm"`).
+///
+/// [`CharacterParts`]: crate::character_control::character_controller::CharacterParts
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct BoneMap {
+    #[serde(default)]
+    pub bones: HashMap<HumanoidBone, String>,
+    #[serde(default = "default_mouth")]
+    pub mouth: String,
+    #[serde(default = "default_g_pencil")]
+    pub g_pencil: String,
+    /// Bones that are not listed turn freely.
+    #[serde(default)]
+    pub limits: HashMap<HumanoidBone, JointLimit>,
+}
+
+fn default_mouth() -> String {
+    "Mouth".to_string()
+}
+
+fn default_g_pencil() -> String {
+    "GPencil".to_string()
+}
+
+impl Default for BoneMap {
+    fn default() -> Self {
+        Self {
+            bones: HashMap::new(),
+            mouth: default_mouth(),
+            g_pencil: default_g_pencil(),
+            limits: HashMap::new(),
+        }
+    }
+}
+
+impl BoneMap {
+    /// The bone map of the model at `model_path`, relative to the `assets` directory.
+    pub fn path(model_path: &str) -> PathBuf {
+        asset_file(model_path).with_extension("bones.ron")
+    }
+
+    pub fn load(model_path: &str) -> Result<Self, String> {
+        let path = Self::path(model_path);
+        let content = fs::read_to_string(&path)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        ron::from_str(&content).map_err(|err| format!("Invalid {}: {}", path.display(), err))
+    }
+
+    /// The glTF node name `bone` is looked up by.
+    pub fn node_name(&self, bone: HumanoidBone) -> String {
+        self.bones
+            .get(&bone)
+            .cloned()
+            .unwrap_or_else(|| bone.to_string())
+    }
+
+    /// The bone a node of the model stands for, if any.
+    pub fn lookup(&self) -> HashMap<String, HumanoidBone> {
+        HumanoidBone::iter()
+            .map(|bone| (self.node_name(bone), bone))
+            .collect()
+    }
+}
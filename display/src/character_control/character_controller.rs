@@ -1,25 +1,64 @@
+use crate::character_control::bone_lengths::{BoneLengths, calibrate_bone_lengths};
+use crate::character_control::foot_lock::{
+    FootContacts, detect_foot_contacts, floor_clearance, root_parent,
+};
 use crate::character_control::find_entity::{debug_named_entity, find_named_entity};
+use crate::character_control::humanoid::{RestPose, capture_rest_pose};
+use crate::character_control::joint_limits::JointLimits;
 use crate::character_control::mouth_control::control_mouth;
 use crate::character_control::move_eyes::move_eyes;
 use crate::character_control::pose::*;
+use crate::character_control::retarget::{RestFrames, measure_rest_frames};
 use crate::character_control::rotate_body::rotate_body;
 use crate::character_control::rotate_hands::rotate_hands;
 use crate::ui::state::GuiState;
+use crate::ui::ui_controller::MouthTextures;
 use bevy::app::Plugin;
 use bevy::prelude::*;
 use bevy_simple_subsecond_system::prelude::*;
 
+use crate::api::ingest::landmarks_drive_body;
+use crate::api::hands_api::CurrentHands;
 use crate::api::pose_api::CurrentPose;
+use std::time::Instant;
 pub struct CharacterControllerPlugin;
 
 impl Plugin for CharacterControllerPlugin {
     fn build(&self, app: &mut bevy::app::App) {
         app.insert_resource(CharacterParts::default())
+            .init_resource::<RestPose>()
+            .init_resource::<BoneLengths>()
+            .init_resource::<FootContacts>()
+            .init_resource::<JointLimits>()
+            .init_resource::<RestFrames>()
             .add_systems(
                 Update,
-                (move_character, rotate_body, control_mouth,rotate_hands, move_eyes),
+                (
+                    (
+                        predict_landmarks,
+                        calibrate_bone_lengths,
+                        move_character,
+                        // After the root has moved, contacts are measured in the world
+                        detect_foot_contacts,
+                        (rotate_body, rotate_hands),
+                    )
+                        .chain()
+                        .run_if(landmarks_drive_body),
+                    // The mouth overlay only exists with the GUI.
+                    control_mouth.run_if(resource_exists::<MouthTextures>),
+                    move_eyes,
+                ),
+            )
+            // Only when a model is loaded, the rest pose doesn't change in between
+            .add_systems(
+                Update,
+                measure_rest_frames
+                    .run_if(resource_changed::<RestPose>.or(resource_changed::<CharacterParts>))
+                    .before(rotate_body)
+                    .before(move_eyes),
             )
             .add_observer(find_named_entity)
+            .add_observer(capture_rest_pose)
             .add_observer(debug_named_entity);
     }
 }
@@ -27,6 +66,7 @@ impl Plugin for CharacterControllerPlugin {
 #[derive(Resource, Default, Debug, Clone, Reflect, PartialEq, Eq, Hash)]
 pub struct CharacterParts {
     pub root: Option<Entity>,
+    pub spine: SpineParts,
     pub neck: Option<Entity>,
 
     pub left_eye: Option<Entity>,
@@ -46,10 +86,19 @@ pub struct CharacterParts {
     pub right_hand: HandParts,
 }
 
+/// The bones between the hips and the neck, from the bottom up. Any of them may be missing.
+#[derive(Resource, Default, Debug, Clone, Reflect, PartialEq, Eq, Hash)]
+pub struct SpineParts {
+    pub spine: Option<Entity>,
+    pub chest: Option<Entity>,
+    pub upper_chest: Option<Entity>,
+}
+
 #[derive(Resource, Default, Debug, Clone, Reflect, PartialEq, Eq, Hash)]
 pub struct LimbParts {
     pub upper: Option<Entity>,
     pub lower: Option<Entity>,
+    pub foot: Option<Entity>,
 }
 
 #[derive(Resource, Default, Debug, Clone, Reflect, PartialEq, Eq, Hash)]
@@ -81,10 +130,34 @@ pub struct ThumbParts {
     pub ip: Option<Entity>,
 }
 
+/// Extrapolates the pose and hands to the current frame, the body systems read the result.
+#[hot]
+fn predict_landmarks(
+    gui_state: Res<GuiState>,
+    mut current_pose: ResMut<CurrentPose>,
+    mut current_hands: ResMut<CurrentHands>,
+) {
+    let now = Instant::now();
+    let config = gui_state.prediction_config();
+
+    if let Some(pose) = current_pose.0.as_mut() {
+        pose.predict(now, &config);
+    }
+    let hands = current_hands.as_mut();
+    for hand in [&mut hands.left_hand, &mut hands.right_hand]
+        .into_iter()
+        .flatten()
+    {
+        hand.predict(now, &config);
+    }
+}
+
 #[hot]
 fn move_character(
     parts: Res<CharacterParts>,
     mut mut_transform_q: Query<&mut Transform>,
+    g_trans_q: Query<&GlobalTransform>,
+    child_of_q: Query<&ChildOf>,
     gui_state: Res<GuiState>,
     current_pose: Res<CurrentPose>,
     // mut gizmos: Gizmos,
@@ -95,8 +168,8 @@ fn move_character(
     };
     let root = parts.root.ok_or("No Root")?;
     let root_position_target = if gui_state.move_root {
-        ((pose.get().landmarks[PoseLandmarkIndex::LeftHip].position
-            + pose.get().landmarks[PoseLandmarkIndex::RightHip].position)
+        ((pose.predicted().landmarks[PoseLandmarkIndex::LeftHip].position
+            + pose.predicted().landmarks[PoseLandmarkIndex::RightHip].position)
             / 2.
             + Vec3::new(0., 2., 0.))
             * gui_state.move_scale
@@ -104,8 +177,18 @@ fn move_character(
         Vec3::new(0., 0., 0.)
     };
 
-    let mut root_transform = mut_transform_q.get_mut(root)?;
+    // Raised by however far the lowest foot would sink below the floor
+    let root_parent = root_parent(&parts, &child_of_q, &g_trans_q);
+    let clearance = floor_clearance(
+        &pose.predicted().world_landmarks,
+        &parts,
+        &root_parent,
+        root_position_target,
+        gui_state.floor_height,
+        &g_trans_q,
+    );
 
-    root_transform.translation = root_position_target;
+    let mut root_transform = mut_transform_q.get_mut(root)?;
+    root_transform.translation = root_position_target + clearance;
     Ok(())
 }
@@ -0,0 +1,236 @@
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::prelude::*;
+
+use crate::api::pose_api::CurrentPose;
+use crate::character_control::character_controller::CharacterParts;
+use crate::character_control::humanoid::HumanoidBone;
+use crate::character_control::limb_ik::{LEFT_LEG, RIGHT_LEG, solve_limb_ik, torso_length};
+use crate::character_control::pose::PoseLandmarkIndex::*;
+use crate::character_control::pose::PoseLandmarks;
+use crate::ui::state::GuiState;
+
+/// A grounded foot is only released once it rises or moves this much past the contact limits,
+/// so it doesn't flicker between locked and free at the threshold.
+const RELEASE_FACTOR: f32 = 1.5;
+
+/// Whether each foot is on the ground, and where it is pinned while it is.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct FootContacts {
+    pub left: FootContact,
+    pub right: FootContact,
+    /// Where the tracked landmarks are placed in the world this frame.
+    space: Option<TrackedSpace>,
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct FootContact {
+    /// Middle of the heel and toes last frame, in world space.
+    previous: Option<Vec3>,
+    /// World position the model's ankle is pinned to while the foot is on the ground.
+    pub lock: Option<Vec3>,
+}
+
+/// Places the hip-centred world landmarks in the avatar's world space. The model's bones point
+/// the same way as the tracked segments, so the two spaces only differ by where the hips are and
+/// the size of the body.
+#[derive(Debug, Clone, Copy)]
+struct TrackedSpace {
+    tracked_hips: Vec3,
+    hips: Vec3,
+    units_per_metre: f32,
+}
+
+impl TrackedSpace {
+    /// Measured once the root has been moved for the frame. Global transforms are only
+    /// propagated at the end of the frame, so the model's hips are moved along with the root.
+    fn new(
+        landmarks: &PoseLandmarks,
+        parts: &CharacterParts,
+        root_parent: &GlobalTransform,
+        root_translation: Vec3,
+        g_trans_q: &Query<&GlobalTransform>,
+    ) -> Option<Self> {
+        let position = |entity: Option<Entity>| {
+            entity
+                .and_then(|entity| g_trans_q.get(entity).ok())
+                .map(|transform| transform.translation())
+        };
+        let bone = |bone: HumanoidBone| position(parts.bone(bone));
+        let left_hip = bone(HumanoidBone::LeftUpperLeg)?;
+        let right_hip = bone(HumanoidBone::RightUpperLeg)?;
+        let left_shoulder = bone(HumanoidBone::LeftUpperArm)?;
+        let right_shoulder = bone(HumanoidBone::RightUpperArm)?;
+
+        let root = parts.root?;
+        let moved = root_parent.transform_point(root_translation) - position(Some(root))?;
+
+        let hips = (left_hip + right_hip) / 2.0;
+        let units_per_metre =
+            hips.distance((left_shoulder + right_shoulder) / 2.0) / torso_length(landmarks);
+        (units_per_metre > 0.0).then(|| Self {
+            tracked_hips: (landmarks[LeftHip].position + landmarks[RightHip].position) / 2.0,
+            hips: hips + moved,
+            units_per_metre,
+        })
+    }
+
+    fn to_world(&self, position: Vec3) -> Vec3 {
+        self.hips + (position - self.tracked_hips) * self.units_per_metre
+    }
+
+    fn to_tracked(&self, position: Vec3) -> Vec3 {
+        self.tracked_hips + (position - self.hips) / self.units_per_metre
+    }
+}
+
+impl FootContacts {
+    /// Bends the legs of grounded feet so the model's ankles stay where they were when the feet
+    /// touched down, whatever the root does.
+    pub fn pin(&self, landmarks: &mut PoseLandmarks, rest: &PoseLandmarks) {
+        let Some(space) = self.space else {
+            return;
+        };
+        for (contact, limb) in [(&self.left, &LEFT_LEG), (&self.right, &RIGHT_LEG)] {
+            if let Some(lock) = contact.lock {
+                solve_limb_ik(landmarks, rest, limb, space.to_tracked(lock));
+            }
+        }
+    }
+}
+
+/// The global transform of the root's parent, the space the root is moved in.
+pub fn root_parent(
+    parts: &CharacterParts,
+    child_of_q: &Query<&ChildOf>,
+    g_trans_q: &Query<&GlobalTransform>,
+) -> GlobalTransform {
+    parts
+        .root
+        .and_then(|root| child_of_q.get(root).ok())
+        .and_then(|child_of| g_trans_q.get(child_of.parent()).ok())
+        .copied()
+        .unwrap_or_default()
+}
+
+/// How far the root at `root_translation` has to rise, in the space of its parent, so the lowest
+/// tracked ankle, heel or toe is not below [`GuiState::floor_height`].
+pub fn floor_clearance(
+    landmarks: &PoseLandmarks,
+    parts: &CharacterParts,
+    root_parent: &GlobalTransform,
+    root_translation: Vec3,
+    floor_height: f32,
+    g_trans_q: &Query<&GlobalTransform>,
+) -> Vec3 {
+    let Some(space) = TrackedSpace::new(landmarks, parts, root_parent, root_translation, g_trans_q)
+    else {
+        return Vec3::ZERO;
+    };
+    let floor = root_parent.transform_point(Vec3::Y * floor_height).y;
+    let lowest = [
+        LeftAnkle,
+        LeftHeel,
+        LeftFootIndex,
+        RightAnkle,
+        RightHeel,
+        RightFootIndex,
+    ]
+    .into_iter()
+    .map(|landmark| space.to_world(landmarks[landmark].position).y)
+    .fold(f32::INFINITY, f32::min);
+
+    if lowest < floor {
+        root_parent
+            .affine()
+            .inverse()
+            .transform_vector3(Vec3::Y * (floor - lowest))
+    } else {
+        Vec3::ZERO
+    }
+}
+
+/// Marks a foot as grounded while it is both close to the floor and slow in the avatar's world,
+/// and pins it where the model's ankle is when it touches down. See [`GuiState::lock_feet`].
+#[hot]
+pub fn detect_foot_contacts(
+    gui_state: Res<GuiState>,
+    time: Res<Time>,
+    current_pose: Res<CurrentPose>,
+    parts: Res<CharacterParts>,
+    transform_q: Query<&Transform>,
+    g_trans_q: Query<&GlobalTransform>,
+    child_of_q: Query<&ChildOf>,
+    mut contacts: ResMut<FootContacts>,
+) {
+    if !gui_state.lock_feet {
+        *contacts = FootContacts::default();
+        return;
+    }
+    let Some(pose) = current_pose.0.as_ref() else {
+        return;
+    };
+    let landmarks = &pose.predicted().world_landmarks;
+    let dt = time.delta_secs().max(f32::EPSILON);
+
+    let root_parent = root_parent(&parts, &child_of_q, &g_trans_q);
+    let Some(space) = parts
+        .root
+        .and_then(|root| transform_q.get(root).ok())
+        .and_then(|root| {
+            TrackedSpace::new(
+                landmarks,
+                &parts,
+                &root_parent,
+                root.translation,
+                &g_trans_q,
+            )
+        })
+    else {
+        *contacts = FootContacts::default();
+        return;
+    };
+    let floor = root_parent
+        .transform_point(Vec3::Y * gui_state.floor_height)
+        .y;
+
+    let contacts = contacts.as_mut();
+    contacts.space = Some(space);
+    for (contact, heel, toes, foot) in [
+        (
+            &mut contacts.left,
+            LeftHeel,
+            LeftFootIndex,
+            parts.left_leg.foot,
+        ),
+        (
+            &mut contacts.right,
+            RightHeel,
+            RightFootIndex,
+            parts.right_leg.foot,
+        ),
+    ] {
+        let heel = space.to_world(landmarks[heel].position);
+        let toes = space.to_world(landmarks[toes].position);
+        let position = (heel + toes) / 2.0;
+        // Compared in metres of the performer, like the thresholds
+        let speed = contact.previous.map_or(f32::INFINITY, |previous| {
+            previous.distance(position) / dt / space.units_per_metre
+        });
+        contact.previous = Some(position);
+        let height = (heel.y.min(toes.y) - floor) / space.units_per_metre;
+
+        let release = if contact.lock.is_some() {
+            RELEASE_FACTOR
+        } else {
+            1.0
+        };
+        if height > gui_state.contact_height * release || speed > gui_state.contact_speed * release
+        {
+            contact.lock = None;
+        } else if contact.lock.is_none() {
+            contact.lock = foot
+                .and_then(|entity| g_trans_q.get(entity).ok())
+                .map(|transform| transform.translation());
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumCount, EnumIter, EnumString};
+
+use crate::character_control::character_controller::CharacterParts;
+
+/// Declares [`HumanoidBone`] and the field of [`CharacterParts`] each bone is stored in.
+macro_rules! humanoid_bones {
+    ($($bone:ident => $($field:ident).+),* $(,)?) => {
+        /// The bones of [`CharacterParts`], named after the Unity/VMC humanoid bones.
+        #[derive(
+            Debug, Clone, Copy, PartialEq, Eq, Hash, EnumCount, EnumIter, Display, EnumString,
+            Serialize, Deserialize, Reflect,
+        )]
+        pub enum HumanoidBone {
+            $($bone),*
+        }
+
+        impl CharacterParts {
+            pub fn bone(&self, bone: HumanoidBone) -> Option<Entity> {
+                match bone {
+                    $(HumanoidBone::$bone => self.$($field).+),*
+                }
+            }
+
+            pub fn bone_mut(&mut self, bone: HumanoidBone) -> &mut Option<Entity> {
+                match bone {
+                    $(HumanoidBone::$bone => &mut self.$($field).+),*
+                }
+            }
+        }
+    };
+}
+
+humanoid_bones! {
+    Hips => root,
+    Spine => spine.spine,
+    Chest => spine.chest,
+    UpperChest => spine.upper_chest,
+    Neck => neck,
+    LeftEye => left_eye,
+    RightEye => right_eye,
+
+    LeftUpperArm => left_arm.upper,
+    LeftLowerArm => left_arm.lower,
+    LeftLowerArmTwist => left_arm.lower_r,
+    RightUpperArm => right_arm.upper,
+    RightLowerArm => right_arm.lower,
+    RightLowerArmTwist => right_arm.lower_r,
+
+    LeftUpperLeg => left_leg.upper,
+    LeftLowerLeg => left_leg.lower,
+    LeftFoot => left_leg.foot,
+    RightUpperLeg => right_leg.upper,
+    RightLowerLeg => right_leg.lower,
+    RightFoot => right_leg.foot,
+
+    LeftHand => left_hand.wrist,
+    LeftThumbIntermediate => left_hand.thumb.mcp,
+    LeftThumbDistal => left_hand.thumb.ip,
+    LeftIndexProximal => left_hand.index.mcp,
+    LeftIndexIntermediate => left_hand.index.pip,
+    LeftIndexDistal => left_hand.index.dip,
+    LeftMiddleProximal => left_hand.middle.mcp,
+    LeftMiddleIntermediate => left_hand.middle.pip,
+    LeftMiddleDistal => left_hand.middle.dip,
+    LeftRingProximal => left_hand.ring.mcp,
+    LeftRingIntermediate => left_hand.ring.pip,
+    LeftRingDistal => left_hand.ring.dip,
+    LeftLittleProximal => left_hand.pinky.mcp,
+    LeftLittleIntermediate => left_hand.pinky.pip,
+    LeftLittleDistal => left_hand.pinky.dip,
+
+    RightHand => right_hand.wrist,
+    RightThumbIntermediate => right_hand.thumb.mcp,
+    RightThumbDistal => right_hand.thumb.ip,
+    RightIndexProximal => right_hand.index.mcp,
+    RightIndexIntermediate => right_hand.index.pip,
+    RightIndexDistal => right_hand.index.dip,
+    RightMiddleProximal => right_hand.middle.mcp,
+    RightMiddleIntermediate => right_hand.middle.pip,
+    RightMiddleDistal => right_hand.middle.dip,
+    RightRingProximal => right_hand.ring.mcp,
+    RightRingIntermediate => right_hand.ring.pip,
+    RightRingDistal => right_hand.ring.dip,
+    RightLittleProximal => right_hand.pinky.mcp,
+    RightLittleIntermediate => right_hand.pinky.pip,
+    RightLittleDistal => right_hand.pinky.dip,
+}
+
+impl CharacterParts {
+    /// Every bone that has been found in the loaded scene.
+    pub fn found_bones(&self) -> impl Iterator<Item = (HumanoidBone, Entity)> + '_ {
+        HumanoidBone::iter().filter_map(|bone| self.bone(bone).map(|entity| (bone, entity)))
+    }
+}
+
+/// Local transforms of every named node as loaded from the model, before any retargeting.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct RestPose(pub bevy::platform::collections::HashMap<Entity, Transform>);
+
+impl RestPose {
+    pub fn rotation(&self, entity: Entity) -> Quat {
+        self.0
+            .get(&entity)
+            .map(|t| t.rotation)
+            .unwrap_or(Quat::IDENTITY)
+    }
+
+    /// The transform of `entity` relative to the model, with it and its ancestors in their rest
+    /// pose. Stops at the first ancestor that is not part of the model.
+    pub fn global_transform(&self, entity: Entity, child_of_q: &Query<&ChildOf>) -> Transform {
+        let mut transform = self.0.get(&entity).copied().unwrap_or_default();
+        let mut current = entity;
+        while let Ok(child_of) = child_of_q.get(current) {
+            current = child_of.parent();
+            let Some(parent) = self.0.get(&current) else {
+                break;
+            };
+            transform = parent.mul_transform(transform);
+        }
+        transform
+    }
+
+    /// The first ancestor of `entity` that is not part of the model, which
+    /// [`RestPose::global_transform`] is relative to.
+    pub fn model_parent(&self, entity: Entity, child_of_q: &Query<&ChildOf>) -> Option<Entity> {
+        let mut current = entity;
+        while let Ok(child_of) = child_of_q.get(current) {
+            current = child_of.parent();
+            if !self.0.contains_key(&current) {
+                return Some(current);
+            }
+        }
+        None
+    }
+}
+
+pub fn capture_rest_pose(
+    _trigger: Trigger<bevy::scene::SceneInstanceReady>,
+    query: Query<(Entity, &Transform), With<Name>>,
+    mut rest_pose: ResMut<RestPose>,
+) {
+    for (entity, transform) in &query {
+        rest_pose.0.entry(entity).or_insert(*transform);
+    }
+}
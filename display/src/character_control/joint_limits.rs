@@ -0,0 +1,175 @@
+use std::f32::consts::PI;
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How far a bone may turn away from its rest rotation relative to its parent, listed per bone in
+/// the `.bones.ron` file of a model. Axes are in the bone's own rest frame, angles in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum JointLimit {
+    /// Turns only around `axis`, between `min` and `max`, e.g. elbows, knees and fingers. The
+    /// axis is picked so bending the joint is a positive angle.
+    Hinge { axis: Vec3, min: f32, max: f32 },
+    /// Swings up to `swing` away from `axis`, the direction the bone points in, and twists up to
+    /// `twist` either way around it, e.g. shoulders, hips and wrists.
+    Cone { axis: Vec3, swing: f32, twist: f32 },
+}
+
+impl JointLimit {
+    /// Limits `rotation`, a turn away from the rest rotation in the bone's rest frame.
+    pub fn clamp(&self, rotation: Quat) -> Quat {
+        match *self {
+            JointLimit::Hinge { axis, min, max } => {
+                let axis = axis.normalize();
+                let (_, twist) = swing_twist(rotation, axis);
+                let angle = twist_angle(twist, axis).clamp(min.to_radians(), max.to_radians());
+                Quat::from_axis_angle(axis, angle)
+            }
+            JointLimit::Cone { axis, swing, twist } => {
+                let axis = axis.normalize();
+                let (swing_rotation, twist_rotation) = swing_twist(rotation, axis);
+
+                let (swing_axis, swing_angle) = swing_rotation.to_axis_angle();
+                let (swing_axis, swing_angle) = if swing_angle > PI {
+                    (-swing_axis, 2.0 * PI - swing_angle)
+                } else {
+                    (swing_axis, swing_angle)
+                };
+                let swing_rotation = if swing_angle > swing.to_radians() {
+                    Quat::from_axis_angle(swing_axis, swing.to_radians())
+                } else {
+                    swing_rotation
+                };
+
+                let twist_angle = twist_angle(twist_rotation, axis)
+                    .clamp(-twist.to_radians(), twist.to_radians());
+                swing_rotation * Quat::from_axis_angle(axis, twist_angle)
+            }
+        }
+    }
+}
+
+/// Splits `rotation` into a swing that moves `axis` and a twist around it, with
+/// `rotation = swing * twist`.
+fn swing_twist(rotation: Quat, axis: Vec3) -> (Quat, Quat) {
+    let projected = axis * rotation.xyz().dot(axis);
+    let twist = Quat::from_xyzw(projected.x, projected.y, projected.z, rotation.w);
+    // A half turn swing leaves no twist to measure
+    let twist = if twist.length_squared() > f32::EPSILON {
+        twist.normalize()
+    } else {
+        Quat::IDENTITY
+    };
+    (rotation * twist.inverse(), twist)
+}
+
+/// Signed angle of a twist around `axis`, in `-PI..=PI`.
+fn twist_angle(twist: Quat, axis: Vec3) -> f32 {
+    let angle = 2.0 * twist.xyz().dot(axis).atan2(twist.w);
+    if angle > PI {
+        angle - 2.0 * PI
+    } else if angle < -PI {
+        angle + 2.0 * PI
+    } else {
+        angle
+    }
+}
+
+/// The [`JointLimit`] of every found bone that has one.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct JointLimits(pub HashMap<Entity, JointLimit>);
+
+impl JointLimits {
+    /// Limits the local `rotation` of `entity` around its local rest rotation `rest`.
+    pub fn apply(&self, entity: Entity, rotation: Quat, rest: Quat) -> Quat {
+        match self.0.get(&entity) {
+            Some(limit) => rest * limit.clamp(rest.inverse() * rotation),
+            None => rotation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_turn(actual: Quat, expected: Quat) {
+        // q and -q are the same rotation
+        assert!(
+            actual.abs_diff_eq(expected, 1e-5) || actual.abs_diff_eq(-expected, 1e-5),
+            "{actual} != {expected}"
+        );
+    }
+
+    #[test]
+    fn swing_and_twist_compose_back() {
+        let axis = Vec3::new(0.0, 1.0, 1.0).normalize();
+        let rotation = Quat::from_rotation_x(0.7) * Quat::from_axis_angle(axis, -1.2);
+        let (swing, twist) = swing_twist(rotation, axis);
+
+        assert_turn(swing * twist, rotation);
+        assert!(twist.xyz().cross(axis).length() < 1e-5);
+        // The swing moves the axis without turning around it
+        assert!(swing.xyz().dot(axis).abs() < 1e-5);
+        assert!((twist_angle(Quat::from_axis_angle(axis, -1.2), axis) + 1.2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn hinges_only_bend_around_their_axis() {
+        let hinge = JointLimit::Hinge {
+            axis: Vec3::X,
+            min: 0.0,
+            max: 150.0,
+        };
+        let bend = Quat::from_rotation_x(60f32.to_radians());
+        assert_turn(hinge.clamp(bend), bend);
+        assert_turn(hinge.clamp(Quat::from_rotation_y(0.3) * bend), bend);
+        assert_turn(
+            hinge.clamp(Quat::from_rotation_x(170f32.to_radians())),
+            Quat::from_rotation_x(150f32.to_radians()),
+        );
+        assert_turn(hinge.clamp(Quat::from_rotation_x(-0.5)), Quat::IDENTITY);
+    }
+
+    #[test]
+    fn cones_limit_swing_and_twist() {
+        let cone = JointLimit::Cone {
+            axis: Vec3::Y,
+            swing: 45.0,
+            twist: 30.0,
+        };
+        let within = Quat::from_rotation_z(0.5) * Quat::from_rotation_y(0.3);
+        assert_turn(cone.clamp(within), within);
+        assert_turn(
+            cone.clamp(Quat::from_rotation_z(-PI / 2.0)),
+            Quat::from_rotation_z(-PI / 4.0),
+        );
+        assert_turn(
+            cone.clamp(Quat::from_rotation_y(-1.5)),
+            Quat::from_rotation_y(-30f32.to_radians()),
+        );
+    }
+
+    #[test]
+    fn limits_are_applied_around_the_rest_rotation() {
+        let entity = Entity::from_raw(1);
+        let limits = JointLimits(HashMap::from_iter([(
+            entity,
+            JointLimit::Hinge {
+                axis: Vec3::X,
+                min: 0.0,
+                max: 90.0,
+            },
+        )]));
+        let rest = Quat::from_rotation_z(1.0);
+
+        let bent = rest * Quat::from_rotation_x(2.0);
+        assert_turn(
+            limits.apply(entity, bent, rest),
+            rest * Quat::from_rotation_x(PI / 2.0),
+        );
+        // Bones without a limit turn freely
+        assert_turn(limits.apply(Entity::from_raw(2), bent, rest), bent);
+    }
+}
@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::character_control::pose::PoseLandmarkIndex::{self, *};
+use crate::character_control::pose::PoseLandmarks;
+use crate::math::two_bone_ik::solve_two_bone_ik;
+
+/// How the upper and lower bones of a limb are aimed, chosen per limb in [`GuiState`].
+///
+/// [`GuiState`]: crate::ui::state::GuiState
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect, Serialize, Deserialize)]
+pub enum LimbSolver {
+    /// Each bone follows the direction of its own segment of the pose.
+    #[default]
+    Fk,
+    /// The bones bend so the model's hand or foot lands on the tracked wrist or ankle.
+    Ik,
+}
+
+/// The joints a limb's two bones run between, and the landmarks that move with its end.
+#[derive(Debug, Clone, Copy)]
+pub struct Limb {
+    pub root: PoseLandmarkIndex,
+    /// Also the pole the limb bends towards.
+    pub mid: PoseLandmarkIndex,
+    pub end: PoseLandmarkIndex,
+    pub carried: &'static [PoseLandmarkIndex],
+}
+
+pub const LEFT_ARM: Limb = Limb {
+    root: LeftShoulder,
+    mid: LeftElbow,
+    end: LeftWrist,
+    carried: &[LeftPinky, LeftIndex, LeftThumb],
+};
+
+pub const RIGHT_ARM: Limb = Limb {
+    root: RightShoulder,
+    mid: RightElbow,
+    end: RightWrist,
+    carried: &[RightPinky, RightIndex, RightThumb],
+};
+
+pub const LEFT_LEG: Limb = Limb {
+    root: LeftHip,
+    mid: LeftKnee,
+    end: LeftAnkle,
+    carried: &[LeftHeel, LeftFootIndex],
+};
+
+pub const RIGHT_LEG: Limb = Limb {
+    root: RightHip,
+    mid: RightKnee,
+    end: RightAnkle,
+    carried: &[RightHeel, RightFootIndex],
+};
+
+/// Distance from the centre of the hips to the centre of the shoulders.
+pub fn torso_length(landmarks: &PoseLandmarks) -> f32 {
+    let shoulders = (landmarks[LeftShoulder].position + landmarks[RightShoulder].position) / 2.0;
+    let hips = (landmarks[LeftHip].position + landmarks[RightHip].position) / 2.0;
+    shoulders.distance(hips)
+}
+
+/// Moves the middle and end joints of `limb` to where the model's own limb, scaled to the
+/// tracked torso, has to put them for its end to reach `target`, usually the tracked end joint.
+/// Aiming the bones along the moved segments then solves the limb.
+///
+/// `rest` are the landmarks of the model's rest pose. Limbs that can't be solved are left as
+/// tracked.
+pub fn solve_limb_ik(
+    landmarks: &mut PoseLandmarks,
+    rest: &PoseLandmarks,
+    limb: &Limb,
+    target: Vec3,
+) {
+    let scale = torso_length(landmarks) / torso_length(rest);
+    let upper_length = rest[limb.root].position.distance(rest[limb.mid].position) * scale;
+    let lower_length = rest[limb.mid].position.distance(rest[limb.end].position) * scale;
+
+    let Some((mid, end)) = solve_two_bone_ik(
+        landmarks[limb.root].position,
+        target,
+        landmarks[limb.mid].position,
+        upper_length,
+        lower_length,
+    ) else {
+        return;
+    };
+
+    let offset = end - landmarks[limb.end].position;
+    landmarks[limb.mid].position = mid;
+    landmarks[limb.end].position = end;
+    for &landmark in limb.carried {
+        landmarks[landmark].position += offset;
+    }
+}
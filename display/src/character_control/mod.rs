@@ -1,9 +1,17 @@
+pub mod bone_lengths;
+pub mod bone_map;
 pub mod character_controller;
 pub mod find_entity;
+pub mod foot_lock;
 pub mod hands;
+pub mod humanoid;
+pub mod joint_limits;
+pub mod limb_ik;
 pub mod pose;
+pub mod retarget;
 pub mod rotate_body;
 pub mod rotate_hands;
+pub mod spine;
 pub mod mouth;
 pub mod move_eyes;
 pub mod mouth_control;
\ No newline at end of file
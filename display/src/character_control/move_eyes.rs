@@ -5,7 +5,10 @@ use crate::api::hands_api::CurrentHands;
 use crate::api::hands_api::HandLandmarkIndex;
 use crate::character_control::character_controller::CharacterParts;
 use crate::character_control::hands::*;
+use crate::character_control::humanoid::RestPose;
+use crate::character_control::retarget::RestFrames;
 use crate::ui::state::GuiState;
+use crate::vrm::{Vrm, VrmLookAtType};
 use bevy::prelude::*;
 use bevy_simple_subsecond_system::prelude::*;
 
@@ -15,8 +18,10 @@ pub fn move_eyes(
     mut mut_transform_q: Query<&mut Transform>,
     gui_state: Res<GuiState>,
     g_trans_q: Query<&GlobalTransform>,
-    child_of_q: Query<&ChildOf>,
+    rest_frames: Res<RestFrames>,
     curr_face: Res<CurrentFace>,
+    vrm: Option<Res<Vrm>>,
+    rest_pose: Res<RestPose>,
 ) -> Result {
     let face = match curr_face.expression.as_ref() {
         Some(p) => p,
@@ -27,6 +32,28 @@ pub fn move_eyes(
 
     let right_eye = parts.right_eye.ok_or("No Right Eye")?;
 
+    // VRM eyes turn within the ranges of the model, expression look-at is left to
+    // apply_vrm_expressions
+    if let Some(look_at) = vrm.as_ref().and_then(|vrm| vrm.look_at) {
+        if look_at.kind == VrmLookAtType::Bone {
+            let (left, right) = look_at.eye_angles(face.look_x, face.look_y);
+            for (eye, (yaw, pitch)) in [(left_eye, left), (right_eye, right)] {
+                // The model faces +Z, so looking right is a turn towards -X and looking up towards +Y
+                let turn = Quat::from_euler(
+                    EulerRot::YXZ,
+                    -yaw.to_radians(),
+                    -pitch.to_radians(),
+                    0.0,
+                );
+                // Turned in model space, the axes of the eye bones themselves are arbitrary
+                let rest = rest_frames.rotation(eye);
+                mut_transform_q.get_mut(eye)?.rotation =
+                    rest_pose.rotation(eye) * rest.inverse() * turn * rest;
+            }
+        }
+        return Ok(());
+    }
+
     // print!("Moving eyes to ({}, {})\n", face.look_x, face.look_y);
 
     {
@@ -1,9 +1,11 @@
+use crate::math::coordinate_frame::CoordinateFrame;
 use crate::math::landmarks::{LandmarkIndex, Landmarks, LandmarksError};
+use crate::math::filter::{Elements, Magnitude};
 use bevy::math::{Mat3, Quat, Vec3};
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::f32::consts::PI;
 use std::ops::{Add, Mul, Sub};
+use strum::EnumCount as _;
 use strum_macros::{EnumCount, EnumIter, FromRepr};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumCount, EnumIter, FromRepr)]
@@ -56,7 +58,7 @@ use bevy_simple_subsecond_system::prelude::*;
 
 use crate::api::pose_api::PoseDataJson;
 
-type PoseLandmarks = Landmarks<PoseLandmarkIndex>;
+pub type PoseLandmarks = Landmarks<PoseLandmarkIndex, { PoseLandmarkIndex::COUNT }>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PoseData {
@@ -97,27 +99,59 @@ impl Mul<f32> for &PoseData {
     }
 }
 
-impl TryFrom<PoseDataJson> for PoseData {
-    type Error = LandmarksError;
-    fn try_from(value: PoseDataJson) -> Result<Self, Self::Error> {
-        let landmarks_vec_ref = value.pose_landmarker_result.landmarks.get(0);
-        let landmarks_vec = match landmarks_vec_ref {
-            Some(v) => v.clone(),
+/// Measured on the world landmarks, which are in metres.
+impl Magnitude for PoseData {
+    fn magnitude(&self) -> f32 {
+        self.world_landmarks.magnitude()
+    }
+}
+
+/// The world landmarks, then the image landmarks.
+impl Elements for PoseData {
+    fn element_count(&self) -> usize {
+        self.world_landmarks.element_count() + self.landmarks.element_count()
+    }
+
+    fn confidence(&self, i: usize) -> f32 {
+        let world = self.world_landmarks.element_count();
+        if i < world {
+            self.world_landmarks.confidence(i)
+        } else {
+            self.landmarks.confidence(i - world)
+        }
+    }
+
+    fn element_magnitude(&self, i: usize) -> f32 {
+        let world = self.world_landmarks.element_count();
+        if i < world {
+            self.world_landmarks.element_magnitude(i)
+        } else {
+            self.landmarks.element_magnitude(i - world)
+        }
+    }
+
+    fn scale_elements(&self, factors: &[f32]) -> Self {
+        let (world, image) = factors.split_at(self.world_landmarks.element_count());
+        PoseData {
+            world_landmarks: self.world_landmarks.scale_elements(world),
+            landmarks: self.landmarks.scale_elements(image),
+        }
+    }
+}
+
+impl PoseData {
+    /// Converts the first detected pose, with the landmarks laid out as `frame`.
+    pub fn from_json(value: &PoseDataJson, frame: &CoordinateFrame) -> Result<Self, LandmarksError> {
+        let landmarks_vec = match value.pose_landmarker_result.landmarks.get(0) {
+            Some(v) => v,
             None => return Err(LandmarksError::NoKeyPoints),
         };
-        let landmarks: PoseLandmarks = match (landmarks_vec).try_into() {
-            Ok(v) => v,
-            Err(err) => return Err(err), // Corrected return for error
-        };
-        let world_landmarks_vec_ref = value.pose_landmarker_result.world_landmarks.get(0);
-        let world_landmarks_vec = match world_landmarks_vec_ref {
-            Some(v) => v.clone(),
+        let landmarks = PoseLandmarks::from_json(landmarks_vec, frame)?;
+        let world_landmarks_vec = match value.pose_landmarker_result.world_landmarks.get(0) {
+            Some(v) => v,
             None => return Err(LandmarksError::NoKeyPoints),
         };
-        let world_landmarks: PoseLandmarks = match world_landmarks_vec.try_into() {
-            Ok(v) => v,
-            Err(err) => return Err(err),
-        };
+        let world_landmarks = PoseLandmarks::from_json(world_landmarks_vec, frame)?;
         Ok(Self {
             world_landmarks,
             landmarks,
@@ -141,7 +175,7 @@ pub fn compute_neck_rotation(pose: &PoseLandmarks) -> Quat {
 
     let rot_matrix = Mat3::from_cols(right, up, forward);
 
-    Quat::from_mat3(&rot_matrix) * Quat::from_rotation_y(-PI / 2. - 0.3) * Quat::from_rotation_x(PI - 0.35) 
+    Quat::from_mat3(&rot_matrix)
 }
 #[hot]
 pub fn compute_root_rotation(pose: &PoseLandmarks) -> Quat {
@@ -156,134 +190,47 @@ pub fn compute_root_rotation(pose: &PoseLandmarks) -> Quat {
     let up = (shoulder_center - hip_center).normalize();
     let right = (lh - rh).normalize();
     let forward = right.cross(up).normalize();
-
-    let rot_matrix = Mat3::from_cols(right, up, forward);
-
-    Quat::from_mat3(&rot_matrix)
-}
-#[hot]
-pub fn compute_left_upper_arm_rotation(pose: &PoseLandmarks) -> Quat {
-    let left_shoulder = pose[LeftShoulder].position;
-    let left_elbow = pose[LeftElbow].position;
-    let left_hip = pose[LeftHip].position;
-
-    let up = (left_elbow - left_shoulder).normalize();
-    let forward = (left_shoulder - left_hip).normalize();
     let right = up.cross(forward).normalize();
-    let forward = right.cross(up).normalize();
 
     let rot_matrix = Mat3::from_cols(right, up, forward);
 
     Quat::from_mat3(&rot_matrix)
 }
-#[hot]
-pub fn compute_left_lower_arm_rotation(pose: &PoseLandmarks) -> Quat {
-    let left_shoulder = pose[LeftShoulder].position;
-    let left_elbow = pose[LeftElbow].position;
-    let left_wrist = pose[LeftWrist].position;
-
-    let up = (left_wrist - left_elbow).normalize();
-    let forward = (left_elbow - left_shoulder).normalize();
-    let right: Vec3 = up.cross(forward).normalize();
-    let forward = right.cross(up).normalize();
 
-    let rot_matrix = Mat3::from_cols(right, up, forward);
-
-    Quat::from_mat3(&rot_matrix)
-}
-#[hot]
-pub fn compute_right_upper_arm_rotation(pose: &PoseLandmarks) -> Quat {
-    let right_shoulder = pose[RightShoulder].position;
-    let right_elbow = pose[RightElbow].position;
-    let right_hip = pose[RightHip].position;
-
-    let up = (right_elbow - right_shoulder).normalize();
-    let forward = (right_shoulder - right_hip).normalize();
-    let right = up.cross(forward).normalize();
-    let forward = right.cross(up).normalize();
-
-    let rot_matrix = Mat3::from_cols(right, up, forward);
-
-    Quat::from_mat3(&rot_matrix)
-}
+/// The hips turned to the line between them, but standing upright.
 #[hot]
-pub fn compute_right_lower_arm_rotation(pose: &PoseLandmarks) -> Quat {
-    let right_shoulder = pose[RightShoulder].position;
-    let right_elbow = pose[RightElbow].position;
-    let right_wrist = pose[RightWrist].position;
+pub fn compute_pelvis_rotation(pose: &PoseLandmarks) -> Quat {
+    let lh = pose[LeftHip].position;
+    let rh = pose[RightHip].position;
 
-    let up = (right_wrist - right_elbow).normalize();
-    let forward = (right_elbow - right_shoulder).normalize();
-    let right = up.cross(forward).normalize();
-    let forward = right.cross(up).normalize();
+    let right = (lh - rh).normalize();
+    let forward = right.cross(Vec3::Y).normalize();
+    let up = forward.cross(right).normalize();
 
     let rot_matrix = Mat3::from_cols(right, up, forward);
 
     Quat::from_mat3(&rot_matrix)
 }
 
+/// Like [`compute_root_rotation`], but turned to the line between the shoulders.
 #[hot]
-pub fn compute_left_upper_leg_rotation(pose: &PoseLandmarks) -> Quat {
-    let right_hip = pose[RightHip].position;
-    let left_hip = pose[LeftHip].position;
-    let left_knee = pose[LeftKnee].position;
-
-    let up = (left_knee - left_hip).normalize();
-    let right = (left_hip - right_hip).normalize();
-    let forward = right.cross(up).normalize();
-    let right = up.cross(forward).normalize();
-
-    let rot_matrix = Mat3::from_cols(right, up, forward);
-
-    Quat::from_mat3(&rot_matrix) * Quat::from_rotation_y(PI)
-}
-
-#[hot]
-pub fn compute_left_lower_leg_rotation(pose: &PoseLandmarks) -> Quat {
-    let left_hip = pose[LeftHip].position;
-    let left_knee = pose[LeftKnee].position;
-    let left_ankle = pose[LeftAnkle].position;
-
-    let up = (left_ankle - left_knee).normalize();
-    let forward = (left_knee - left_hip).normalize();
-    let right = up.cross(forward).normalize();
-    let forward = right.cross(up).normalize();
-
-    let rot_matrix = Mat3::from_cols(right, up, forward);
-
-    Quat::from_mat3(&rot_matrix) * Quat::from_rotation_y(-PI / 2.)
-}
+pub fn compute_chest_rotation(pose: &PoseLandmarks) -> Quat {
+    let ls = pose[LeftShoulder].position;
+    let rs = pose[RightShoulder].position;
+    let lh = pose[LeftHip].position;
+    let rh = pose[RightHip].position;
 
-#[hot]
-pub fn compute_right_upper_leg_rotation(pose: &PoseLandmarks) -> Quat {
-    let left_hip = pose[LeftHip].position;
-    let right_hip = pose[RightHip].position;
-    let right_knee = pose[RightKnee].position;
+    let shoulder_center = (ls + rs) * 0.5;
+    let hip_center = (lh + rh) * 0.5;
 
-    let up = (right_knee - right_hip).normalize();
-    let right = (right_hip - left_hip).normalize();
+    let up = (shoulder_center - hip_center).normalize();
+    let right = (ls - rs).normalize();
     let forward = right.cross(up).normalize();
     let right = up.cross(forward).normalize();
 
     let rot_matrix = Mat3::from_cols(right, up, forward);
 
-    Quat::from_mat3(&rot_matrix) * Quat::from_rotation_y(PI)
-}
-
-#[hot]
-pub fn compute_right_lower_leg_rotation(pose: &PoseLandmarks) -> Quat {
-    let right_hip = pose[RightHip].position;
-    let right_knee = pose[RightKnee].position;
-    let right_ankle = pose[RightAnkle].position;
-
-    let up = (right_ankle - right_knee).normalize();
-    let forward = (right_knee - right_hip).normalize();
-    let right = up.cross(forward).normalize();
-    let forward = right.cross(up).normalize();
-
-    let rot_matrix = Mat3::from_cols(right, up, forward);
-
-    Quat::from_mat3(&rot_matrix) * Quat::from_rotation_y(PI / 2.)
+    Quat::from_mat3(&rot_matrix)
 }
 
 #[hot]
@@ -301,7 +248,7 @@ pub fn compute_left_lower_arm_r_rotation(pose: &PoseLandmarks) -> Quat {
 
     let rot_matrix = Mat3::from_cols(right, up, forward);
 
-    Quat::from_mat3(&rot_matrix) * Quat::from_rotation_x(PI / 2.) * Quat::from_rotation_y(PI)
+    Quat::from_mat3(&rot_matrix)
 }
 
 #[hot]
@@ -319,5 +266,14 @@ pub fn compute_right_lower_arm_r_rotation(pose: &PoseLandmarks) -> Quat {
 
     let rot_matrix = Mat3::from_cols(right, up, forward);
 
-    Quat::from_mat3(&rot_matrix) * Quat::from_rotation_x(PI / 2.)
+    Quat::from_mat3(&rot_matrix)
+}
+
+/// Direction of the limb segment from joint `from` to joint `to`.
+pub fn compute_segment_direction(
+    pose: &PoseLandmarks,
+    from: PoseLandmarkIndex,
+    to: PoseLandmarkIndex,
+) -> Vec3 {
+    (pose[to].position - pose[from].position).normalize()
 }
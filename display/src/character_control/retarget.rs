@@ -0,0 +1,142 @@
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+use crate::character_control::character_controller::CharacterParts;
+use crate::character_control::humanoid::{HumanoidBone, RestPose};
+use crate::character_control::pose::PoseLandmarkIndex::{self, *};
+use crate::character_control::pose::{PoseLandmarks, compute_root_rotation};
+use crate::math::landmarks::Landmark;
+
+/// The frame of a tracked subject standing upright and facing the camera, which looks down -Z.
+pub const FACING_CAMERA: Quat = Quat::from_xyzw(0.0, 1.0, 0.0, 0.0);
+
+/// Distance of the ears and nose from the neck when laying out the face of the rest pose. Only
+/// the directions between them matter.
+const HEAD_RADIUS: f32 = 0.08;
+
+/// The bone each body landmark sits on, `None` for the face, which has no bones.
+fn landmark_bone(index: PoseLandmarkIndex) -> Option<HumanoidBone> {
+    use HumanoidBone as B;
+
+    Some(match index {
+        LeftShoulder => B::LeftUpperArm,
+        RightShoulder => B::RightUpperArm,
+        LeftElbow => B::LeftLowerArm,
+        RightElbow => B::RightLowerArm,
+        LeftWrist => B::LeftHand,
+        RightWrist => B::RightHand,
+        LeftPinky => B::LeftLittleProximal,
+        RightPinky => B::RightLittleProximal,
+        LeftIndex => B::LeftIndexProximal,
+        RightIndex => B::RightIndexProximal,
+        LeftThumb => B::LeftThumbIntermediate,
+        RightThumb => B::RightThumbIntermediate,
+        LeftHip => B::LeftUpperLeg,
+        RightHip => B::RightUpperLeg,
+        LeftKnee => B::LeftLowerLeg,
+        RightKnee => B::RightLowerLeg,
+        LeftAnkle | LeftHeel | LeftFootIndex => B::LeftFoot,
+        RightAnkle | RightHeel | RightFootIndex => B::RightFoot,
+        _ => return None,
+    })
+}
+
+/// The rest pose of the model in its own space, measured once whenever the model or its bones
+/// change rather than every frame, see [`measure_rest_frames`].
+#[derive(Resource, Debug, Clone)]
+pub struct RestFrames {
+    /// See [`rest_landmarks`].
+    pub landmarks: PoseLandmarks,
+    /// Rest rotation of every named node relative to the model.
+    pub rotations: HashMap<Entity, Quat>,
+}
+
+impl Default for RestFrames {
+    fn default() -> Self {
+        Self {
+            landmarks: PoseLandmarks::from_fn(|_| Landmark {
+                position: Vec3::NAN,
+                visibility: 1.0,
+            }),
+            rotations: HashMap::default(),
+        }
+    }
+}
+
+impl RestFrames {
+    pub fn rotation(&self, entity: Entity) -> Quat {
+        self.rotations
+            .get(&entity)
+            .copied()
+            .unwrap_or(Quat::IDENTITY)
+    }
+}
+
+pub fn measure_rest_frames(
+    parts: Res<CharacterParts>,
+    rest_pose: Res<RestPose>,
+    child_of_q: Query<&ChildOf>,
+    mut rest_frames: ResMut<RestFrames>,
+) {
+    let globals: HashMap<Entity, Transform> = rest_pose
+        .0
+        .keys()
+        .map(|&entity| (entity, rest_pose.global_transform(entity, &child_of_q)))
+        .collect();
+    rest_frames.landmarks = rest_landmarks(&parts, &globals);
+    rest_frames.rotations = globals
+        .into_iter()
+        .map(|(entity, transform)| (entity, transform.rotation))
+        .collect();
+}
+
+/// The rest pose of the model laid out as pose landmarks, so every frame and direction of
+/// [`crate::character_control::pose`] can be measured on the model the same way as on the
+/// tracked subject. `globals` are the rest transforms of the bones relative to the model.
+///
+/// Landmarks on bones the model does not have are NaN, and so is anything computed from them.
+fn rest_landmarks(parts: &CharacterParts, globals: &HashMap<Entity, Transform>) -> PoseLandmarks {
+    let position = |bone: HumanoidBone| {
+        parts
+            .bone(bone)
+            .and_then(|entity| globals.get(&entity))
+            .map_or(Vec3::NAN, |transform| transform.translation)
+    };
+    let mut landmarks = PoseLandmarks::from_fn(|i| Landmark {
+        position: PoseLandmarkIndex::from_repr(i)
+            .and_then(landmark_bone)
+            .map_or(Vec3::NAN, position),
+        visibility: 1.0,
+    });
+
+    // The neck is aimed from the nose and ears, lay them out around the neck looking the same way
+    // as the hips
+    let hips = compute_root_rotation(&landmarks);
+    let neck = position(HumanoidBone::Neck);
+    let left = hips * Vec3::X * HEAD_RADIUS;
+    landmarks[Nose].position = neck + hips * Vec3::Z * HEAD_RADIUS;
+    landmarks[LeftEar].position = neck + left;
+    landmarks[RightEar].position = neck - left;
+    landmarks
+}
+
+/// Global rotation of a bone whose frame is measured at `tracked`, turned away from its rest
+/// rotation by as much as the tracked frame is turned away from the frame measured on the rest
+/// pose. `None` when the rest frame could not be measured.
+pub fn retarget_frame(tracked: Quat, rest_frame: Quat, rest_rotation: Quat) -> Option<Quat> {
+    rest_frame
+        .is_finite()
+        .then(|| (tracked * rest_frame.inverse() * rest_rotation).normalize())
+}
+
+/// Global rotation of a limb segment pointing along `tracked`, swung from its rest rotation by the
+/// shortest arc from its rest direction. `None` when the rest direction could not be measured.
+pub fn retarget_direction(
+    tracked: Vec3,
+    rest_direction: Vec3,
+    rest_rotation: Quat,
+) -> Option<Quat> {
+    rest_direction
+        .is_finite()
+        .then(|| Quat::from_rotation_arc(rest_direction, tracked) * rest_rotation)
+}
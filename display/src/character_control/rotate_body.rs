@@ -1,10 +1,21 @@
 use crate::api::pose_api::CurrentPose;
+use crate::character_control::bone_lengths::BoneLengths;
+use crate::character_control::foot_lock::FootContacts;
+use crate::character_control::humanoid::RestPose;
+use crate::character_control::joint_limits::JointLimits;
+use crate::character_control::limb_ik::{
+    LEFT_ARM, LEFT_LEG, LimbSolver, RIGHT_ARM, RIGHT_LEG, solve_limb_ik,
+};
+use crate::character_control::pose::PoseLandmarkIndex::*;
 use crate::character_control::pose::*;
+use crate::character_control::retarget::{
+    FACING_CAMERA, RestFrames, retarget_direction, retarget_frame,
+};
+use crate::character_control::spine::{spine_frames, spine_weights};
 use crate::ui::state::GuiState;
 use bevy::prelude::*;
 use bevy_simple_subsecond_system::prelude::*;
 use paste::paste;
-use std::f32::consts::PI;
 
 #[hot(hot_patch_signature = true)]
 pub fn rotate_body(
@@ -14,12 +25,39 @@ pub fn rotate_body(
     child_of_q: Query<&ChildOf>,
     gui_state: Res<GuiState>,
     current_pose: Res<CurrentPose>,
+    bone_lengths: Res<BoneLengths>,
+    rest_pose: Res<RestPose>,
+    rest_frames: Res<RestFrames>,
+    foot_contacts: Res<FootContacts>,
+    joint_limits: Res<JointLimits>,
 ) -> Result {
     let pose = match current_pose.0.as_ref() {
         Some(p) => p,
         None => return Ok(()),
     };
-    let landmarks = &pose.get().world_landmarks;
+    let rest = &rest_frames.landmarks;
+    let mut landmarks = pose.predicted().world_landmarks.clone();
+    // Before IK and the foot lock, which aim for the refitted ends with the model's own lengths
+    if gui_state.constrain_bone_lengths {
+        bone_lengths.constrain(&mut landmarks);
+    }
+    for (solver, limb) in [
+        (gui_state.left_arm_solver, &LEFT_ARM),
+        (gui_state.right_arm_solver, &RIGHT_ARM),
+        (gui_state.left_leg_solver, &LEFT_LEG),
+        (gui_state.right_leg_solver, &RIGHT_LEG),
+    ] {
+        if solver == LimbSolver::Ik {
+            let target = landmarks[limb.end].position;
+            solve_limb_ik(&mut landmarks, rest, limb, target);
+        }
+    }
+    if gui_state.lock_feet {
+        foot_contacts.pin(&mut landmarks, rest);
+    }
+    let landmarks = &landmarks;
+    let rest_rotation = |entity: Entity| rest_frames.rotation(entity);
+
     let root = parts.root;
     let neck = parts.neck;
 
@@ -38,80 +76,109 @@ pub fn rotate_body(
     let right_upper_leg = parts.right_leg.upper;
     let right_lower_leg = parts.right_leg.lower;
 
-    let mut rotate_g = |entity: &Option<Entity>, rotation: Quat, name: &str| -> Result {
-        let entity = entity.ok_or(format!("No unable to find {}", name))?;
+    // The rest rotations are relative to the model, so the parents' are taken in the same space
+    let model_r = root
+        .and_then(|root| rest_pose.model_parent(root, &child_of_q))
+        .and_then(|parent| g_trans_q.get(parent).ok())
+        .map_or(Quat::IDENTITY, GlobalTransform::rotation);
+
+    // Sets the rotation of a bone relative to the model, `None` keeps its rest rotation.
+    let mut rotate_g = |entity: Entity, rotation: Option<Quat>| -> Result {
         let mut transform = mut_transform_q.get_mut(entity)?;
+        let Some(rotation) = rotation else {
+            transform.rotation = rest_pose.rotation(entity);
+            return Ok(());
+        };
         let parent = child_of_q.get(entity)?.parent();
-        let parent_r = g_trans_q.get(parent)?.rotation();
-        transform.rotation = parent_r.inverse() * rotation;
+        let parent_r = model_r.inverse() * g_trans_q.get(parent)?.rotation();
+        transform.rotation =
+            joint_limits.apply(entity, parent_r.inverse() * rotation, rest_pose.rotation(entity));
         Ok(())
     };
 
     // Macro definition using the `paste` crate
     macro_rules! rotate_part {
-        ($part:ident, $default_rotation:expr) => {
-            // `paste!` concatenates identifiers at compile time.
-            // [< ... >] is the syntax used by `paste` to merge items.
-            paste! {
-                {
-                    let rotation = if gui_state.[<rotate_ $part>] {
-                        // Creates the function call, e.g., `compute_left_upper_armrust-analyzer-diagnostics-view:/diagnostic%20message%20[3]?3#file:///mnt/d/repos/mocap-render/src/character_control/character_controller.rs_rotation(landmarks)`
-                        [<compute_ $part _rotation>](landmarks)
-                    } else {
-                        $default_rotation
-                    };
-                    // `stringify!` converts the identifier to a string literal, e.g., "left_upper_arm"
-                    rotate_g(&$part, rotation, stringify!($part))?;
-                }
-            }
+        // Aimed by a whole frame, turned from its rest rotation as much as the tracked frame is
+        // turned from the same frame measured on the rest pose.
+        ($part:ident) => {
+            rotate_part!($part, |entity| retarget_frame(
+                // `paste!` concatenates identifiers at compile time, e.g. `compute_neck_rotation`
+                paste! { [<compute_ $part _rotation>] }(landmarks),
+                paste! { [<compute_ $part _rotation>] }(rest),
+                rest_rotation(entity),
+            ))
+        };
+        // A limb segment, swung from its rest rotation to point from joint `$from` to `$to`.
+        ($part:ident, $from:ident, $to:ident) => {
+            rotate_part!($part, |entity| retarget_direction(
+                compute_segment_direction(landmarks, $from, $to),
+                compute_segment_direction(rest, $from, $to),
+                rest_rotation(entity),
+            ))
         };
+        ($part:ident, $tracked:expr) => {{
+            let tracked = $tracked;
+            // `stringify!` converts the identifier to a string literal, e.g., "left_upper_arm"
+            let entity = $part.ok_or(format!("No unable to find {}", stringify!($part)))?;
+            let rotation = if paste! { gui_state.[<rotate_ $part>] } {
+                tracked(entity)
+            } else {
+                None
+            };
+            rotate_g(entity, rotation)?;
+        }};
     }
 
-    rotate_part!(root, Quat::from_rotation_y(PI));
-
-    rotate_part!(neck, Quat::IDENTITY * Quat::from_rotation_y(PI / 2.));
-
-    rotate_part!(
-        left_upper_arm,
-        Quat::from_rotation_y(PI / 2.) * Quat::from_rotation_x(-PI / 2.)
-    );
-    rotate_part!(
-        right_upper_arm,
-        Quat::from_rotation_y(-PI / 2.) * Quat::from_rotation_x(-PI / 2.)
-    );
-
-    rotate_part!(
-        left_lower_arm,
-        Quat::from_rotation_y(PI / 2.) * Quat::from_rotation_x(-PI / 2.)
-    );
-    rotate_part!(
-        right_lower_arm,
-        Quat::from_rotation_y(-PI / 2.) * Quat::from_rotation_x(-PI / 2.)
-    );
-
-    rotate_part!(
-        left_upper_leg,
-        Quat::from_rotation_y(PI / 2.) * Quat::from_rotation_x(PI)
-    );
-
-    rotate_part!(
-        left_lower_leg,
-        Quat::from_rotation_y(PI / 2.) * Quat::from_rotation_x(PI)
-    );
-
-    rotate_part!(
-        right_upper_leg,
-        Quat::from_rotation_y(-PI / 2.) * Quat::from_rotation_x(PI)
-    );
-
-    rotate_part!(
-        right_lower_leg,
-        Quat::from_rotation_y(-PI / 2.) * Quat::from_rotation_x(PI)
-    );
-
-    rotate_part!(left_lower_arm_r, Quat::from_rotation_y(PI / 2.));
-
-    rotate_part!(right_lower_arm_r, Quat::from_rotation_y(-PI / 2.));
+    // The hips and spine share the bend and twist of the torso
+    let weights = spine_weights(&parts, &gui_state);
+    let tracked_spine = spine_frames(landmarks, weights);
+    let rest_spine = spine_frames(rest, weights);
+
+    let root = root.ok_or("No unable to find root")?;
+    // Without tracking the root still turns the model to face the camera like the user does
+    let root_frame = if gui_state.rotate_root {
+        tracked_spine[0]
+    } else {
+        FACING_CAMERA
+    };
+    rotate_g(
+        root,
+        retarget_frame(root_frame, rest_spine[0], rest_rotation(root)),
+    )?;
+
+    let spine = [
+        parts.spine.spine,
+        parts.spine.chest,
+        parts.spine.upper_chest,
+    ];
+    for (i, bone) in spine.into_iter().enumerate() {
+        let Some(bone) = bone else {
+            continue;
+        };
+        let rotation = if gui_state.rotate_spine {
+            retarget_frame(tracked_spine[i + 1], rest_spine[i + 1], rest_rotation(bone))
+        } else {
+            None
+        };
+        rotate_g(bone, rotation)?;
+    }
+
+    rotate_part!(neck);
+
+    rotate_part!(left_upper_arm, LeftShoulder, LeftElbow);
+    rotate_part!(right_upper_arm, RightShoulder, RightElbow);
+
+    rotate_part!(left_lower_arm, LeftElbow, LeftWrist);
+    rotate_part!(right_lower_arm, RightElbow, RightWrist);
+
+    rotate_part!(left_upper_leg, LeftHip, LeftKnee);
+    rotate_part!(left_lower_leg, LeftKnee, LeftAnkle);
+
+    rotate_part!(right_upper_leg, RightHip, RightKnee);
+    rotate_part!(right_lower_leg, RightKnee, RightAnkle);
+
+    rotate_part!(left_lower_arm_r);
+    rotate_part!(right_lower_arm_r);
 
     Ok(())
 }
@@ -4,6 +4,8 @@ use crate::api::hands_api::CurrentHands;
 use crate::api::hands_api::HandLandmarkIndex;
 use crate::character_control::character_controller::CharacterParts;
 use crate::character_control::hands::*;
+use crate::character_control::humanoid::RestPose;
+use crate::character_control::joint_limits::JointLimits;
 use crate::ui::state::GuiState;
 use bevy::prelude::*;
 use bevy_simple_subsecond_system::prelude::*;
@@ -16,6 +18,8 @@ pub fn rotate_hands(
     child_of_q: Query<&ChildOf>,
     gui_state: Res<GuiState>,
     current_hands: Res<CurrentHands>,
+    rest_pose: Res<RestPose>,
+    joint_limits: Res<JointLimits>,
 ) -> Result {
     let left_hand_parts = &parts.left_hand;
     let right_hand_parts = &parts.right_hand;
@@ -29,7 +33,8 @@ pub fn rotate_hands(
         let mut transform = mut_transform_q.get_mut(entity)?;
         let parent = child_of_q.get(entity)?.parent();
         let parent_r = g_trans_q.get(parent)?.rotation();
-        transform.rotation = parent_r.inverse() * rotation;
+        transform.rotation =
+            joint_limits.apply(entity, parent_r.inverse() * rotation, rest_pose.rotation(entity));
         Ok(())
     };
 
@@ -50,10 +55,10 @@ pub fn rotate_hands(
     {
         let mcp: Option<Entity> = left_hand_parts.thumb.mcp;
         let mcp_r = compute_mcp_rotation_thumb(
-            &left_hand.get().landmarks[ThumbMcp].position,
-            &left_hand.get().landmarks[ThumbMcp].position,
-            &left_hand.get().landmarks[ThumbIp].position,
-            &left_hand.get().landmarks[IndexFingerMcp].position,
+            &left_hand.predicted().landmarks[ThumbMcp].position,
+            &left_hand.predicted().landmarks[ThumbMcp].position,
+            &left_hand.predicted().landmarks[ThumbIp].position,
+            &left_hand.predicted().landmarks[IndexFingerMcp].position,
         );
         rotate_g(&mcp, mcp_r, "thumb_pip", &mut mut_transform_q)?;
     }
@@ -61,10 +66,10 @@ pub fn rotate_hands(
     {
         let ip = left_hand_parts.thumb.ip;
         let ip_r = compute_dip_rotation_thumb(
-            &left_hand.get().landmarks[ThumbMcp].position,
-            &left_hand.get().landmarks[ThumbIp].position,
-            &left_hand.get().landmarks[ThumbTip].position,
-            &left_hand.get().landmarks[IndexFingerMcp].position,
+            &left_hand.predicted().landmarks[ThumbMcp].position,
+            &left_hand.predicted().landmarks[ThumbIp].position,
+            &left_hand.predicted().landmarks[ThumbTip].position,
+            &left_hand.predicted().landmarks[IndexFingerMcp].position,
         );
         rotate_g(&ip, ip_r, "thumb_dip", &mut mut_transform_q)?;
     }
@@ -73,10 +78,10 @@ pub fn rotate_hands(
         let mcp = left_hand_parts.index.mcp;
         let mcp_r = if gui_state.rotate_index_cmp {
             compute_mcp_rotation(
-                &left_hand.get().landmarks[IndexFingerMcp].position,
-                &left_hand.get().landmarks[IndexFingerPip].position,
-                &left_hand.get().landmarks[IndexFingerDip].position,
-                &left_hand.get().landmarks[ThumbMcp].position,
+                &left_hand.predicted().landmarks[IndexFingerMcp].position,
+                &left_hand.predicted().landmarks[IndexFingerPip].position,
+                &left_hand.predicted().landmarks[IndexFingerDip].position,
+                &left_hand.predicted().landmarks[ThumbMcp].position,
             )
         } else {
             Quat::IDENTITY
@@ -87,10 +92,10 @@ pub fn rotate_hands(
     {
         let pip = left_hand_parts.index.pip;
         let pip_r = compute_pip_rotation(
-            &left_hand.get().landmarks[IndexFingerMcp].position,
-            &left_hand.get().landmarks[IndexFingerPip].position,
-            &left_hand.get().landmarks[IndexFingerDip].position,
-            &left_hand.get().landmarks[ThumbMcp].position,
+            &left_hand.predicted().landmarks[IndexFingerMcp].position,
+            &left_hand.predicted().landmarks[IndexFingerPip].position,
+            &left_hand.predicted().landmarks[IndexFingerDip].position,
+            &left_hand.predicted().landmarks[ThumbMcp].position,
         );
         rotate_g(&pip, pip_r, "index_pip", &mut mut_transform_q)?;
     }
@@ -98,10 +103,10 @@ pub fn rotate_hands(
     {
         let dip = left_hand_parts.index.dip;
         let dip_r = compute_dip_rotation(
-            &left_hand.get().landmarks[IndexFingerMcp].position,
-            &left_hand.get().landmarks[IndexFingerDip].position,
-            &left_hand.get().landmarks[IndexFingerTip].position,
-            &left_hand.get().landmarks[ThumbMcp].position,
+            &left_hand.predicted().landmarks[IndexFingerMcp].position,
+            &left_hand.predicted().landmarks[IndexFingerDip].position,
+            &left_hand.predicted().landmarks[IndexFingerTip].position,
+            &left_hand.predicted().landmarks[ThumbMcp].position,
         );
         rotate_g(&dip, dip_r, "index_dip", &mut mut_transform_q)?;
     }
@@ -109,10 +114,10 @@ pub fn rotate_hands(
     {
         let mcp = left_hand_parts.middle.mcp;
         let mcp_r = compute_mcp_rotation(
-            &left_hand.get().landmarks[MiddleFingerMcp].position,
-            &left_hand.get().landmarks[MiddleFingerPip].position,
-            &left_hand.get().landmarks[MiddleFingerDip].position,
-            &left_hand.get().landmarks[IndexFingerMcp].position,
+            &left_hand.predicted().landmarks[MiddleFingerMcp].position,
+            &left_hand.predicted().landmarks[MiddleFingerPip].position,
+            &left_hand.predicted().landmarks[MiddleFingerDip].position,
+            &left_hand.predicted().landmarks[IndexFingerMcp].position,
         );
         rotate_g(&mcp, mcp_r, "middle_mcp", &mut mut_transform_q)?;
     }
@@ -120,10 +125,10 @@ pub fn rotate_hands(
     {
         let pip = left_hand_parts.middle.pip;
         let pip_r = compute_pip_rotation(
-            &left_hand.get().landmarks[MiddleFingerMcp].position,
-            &left_hand.get().landmarks[MiddleFingerPip].position,
-            &left_hand.get().landmarks[MiddleFingerDip].position,
-            &left_hand.get().landmarks[IndexFingerMcp].position,
+            &left_hand.predicted().landmarks[MiddleFingerMcp].position,
+            &left_hand.predicted().landmarks[MiddleFingerPip].position,
+            &left_hand.predicted().landmarks[MiddleFingerDip].position,
+            &left_hand.predicted().landmarks[IndexFingerMcp].position,
         );
         rotate_g(&pip, pip_r, "middle_pip", &mut mut_transform_q)?;
     }
@@ -132,10 +137,10 @@ pub fn rotate_hands(
         let dip = left_hand_parts.middle.dip;
 
         let dip_r = compute_dip_rotation(
-            &left_hand.get().landmarks[MiddleFingerMcp].position,
-            &left_hand.get().landmarks[MiddleFingerDip].position,
-            &left_hand.get().landmarks[MiddleFingerTip].position,
-            &left_hand.get().landmarks[IndexFingerMcp].position,
+            &left_hand.predicted().landmarks[MiddleFingerMcp].position,
+            &left_hand.predicted().landmarks[MiddleFingerDip].position,
+            &left_hand.predicted().landmarks[MiddleFingerTip].position,
+            &left_hand.predicted().landmarks[IndexFingerMcp].position,
         );
         rotate_g(&dip, dip_r, "middle_dip", &mut mut_transform_q)?;
     }
@@ -143,10 +148,10 @@ pub fn rotate_hands(
     {
         let mcp = left_hand_parts.ring.mcp;
         let mcp_r = compute_mcp_rotation(
-            &left_hand.get().landmarks[RingFingerMcp].position,
-            &left_hand.get().landmarks[RingFingerPip].position,
-            &left_hand.get().landmarks[RingFingerDip].position,
-            &left_hand.get().landmarks[MiddleFingerMcp].position,
+            &left_hand.predicted().landmarks[RingFingerMcp].position,
+            &left_hand.predicted().landmarks[RingFingerPip].position,
+            &left_hand.predicted().landmarks[RingFingerDip].position,
+            &left_hand.predicted().landmarks[MiddleFingerMcp].position,
         );
         rotate_g(&mcp, mcp_r, "ring_mcp", &mut mut_transform_q)?;
     }
@@ -154,10 +159,10 @@ pub fn rotate_hands(
     {
         let pip = left_hand_parts.ring.pip;
         let pip_r = compute_pip_rotation(
-            &left_hand.get().landmarks[RingFingerMcp].position,
-            &left_hand.get().landmarks[RingFingerPip].position,
-            &left_hand.get().landmarks[RingFingerDip].position,
-            &left_hand.get().landmarks[MiddleFingerMcp].position,
+            &left_hand.predicted().landmarks[RingFingerMcp].position,
+            &left_hand.predicted().landmarks[RingFingerPip].position,
+            &left_hand.predicted().landmarks[RingFingerDip].position,
+            &left_hand.predicted().landmarks[MiddleFingerMcp].position,
         );
         rotate_g(&pip, pip_r, "ring_pip", &mut mut_transform_q)?;
     }
@@ -165,10 +170,10 @@ pub fn rotate_hands(
     {
         let dip = left_hand_parts.ring.dip;
         let dip_r = compute_dip_rotation(
-            &left_hand.get().landmarks[RingFingerMcp].position,
-            &left_hand.get().landmarks[RingFingerDip].position,
-            &left_hand.get().landmarks[RingFingerTip].position,
-            &left_hand.get().landmarks[MiddleFingerMcp].position,
+            &left_hand.predicted().landmarks[RingFingerMcp].position,
+            &left_hand.predicted().landmarks[RingFingerDip].position,
+            &left_hand.predicted().landmarks[RingFingerTip].position,
+            &left_hand.predicted().landmarks[MiddleFingerMcp].position,
         );
         rotate_g(&dip, dip_r, "ring_dip", &mut mut_transform_q)?;
     }
@@ -176,10 +181,10 @@ pub fn rotate_hands(
     {
         let mcp = left_hand_parts.pinky.mcp;
         let mcp_r = compute_mcp_rotation(
-            &left_hand.get().landmarks[PinkyMcp].position,
-            &left_hand.get().landmarks[PinkyPip].position,
-            &left_hand.get().landmarks[PinkyDip].position,
-            &left_hand.get().landmarks[RingFingerMcp].position,
+            &left_hand.predicted().landmarks[PinkyMcp].position,
+            &left_hand.predicted().landmarks[PinkyPip].position,
+            &left_hand.predicted().landmarks[PinkyDip].position,
+            &left_hand.predicted().landmarks[RingFingerMcp].position,
         );
         rotate_g(&mcp, mcp_r, "pinky_mcp", &mut mut_transform_q)?;
     }
@@ -187,10 +192,10 @@ pub fn rotate_hands(
     {
         let pip = left_hand_parts.pinky.pip;
         let pip_r = compute_pip_rotation(
-            &left_hand.get().landmarks[PinkyMcp].position,
-            &left_hand.get().landmarks[PinkyPip].position,
-            &left_hand.get().landmarks[PinkyDip].position,
-            &left_hand.get().landmarks[RingFingerMcp].position,
+            &left_hand.predicted().landmarks[PinkyMcp].position,
+            &left_hand.predicted().landmarks[PinkyPip].position,
+            &left_hand.predicted().landmarks[PinkyDip].position,
+            &left_hand.predicted().landmarks[RingFingerMcp].position,
         );
         rotate_g(&pip, pip_r, "pinky_pip", &mut mut_transform_q)?;
     }
@@ -198,10 +203,10 @@ pub fn rotate_hands(
     {
         let dip = left_hand_parts.pinky.dip;
         let dip_r = compute_dip_rotation(
-            &left_hand.get().landmarks[PinkyMcp].position,
-            &left_hand.get().landmarks[PinkyDip].position,
-            &left_hand.get().landmarks[PinkyTip].position,
-            &left_hand.get().landmarks[RingFingerMcp].position,
+            &left_hand.predicted().landmarks[PinkyMcp].position,
+            &left_hand.predicted().landmarks[PinkyDip].position,
+            &left_hand.predicted().landmarks[PinkyTip].position,
+            &left_hand.predicted().landmarks[RingFingerMcp].position,
         );
         rotate_g(&dip, dip_r, "pinky_dip", &mut mut_transform_q)?;
     }
@@ -209,10 +214,10 @@ pub fn rotate_hands(
     {
         let left_palm = left_hand_parts.wrist;
         let l_palm_r = compute_left_palm_rotation(
-            &left_hand.get().landmarks[Wrist].position,
-            &left_hand.get().landmarks[MiddleFingerMcp].position,
-            &left_hand.get().landmarks[ThumbCmc].position,
-            &left_hand.get().landmarks[PinkyMcp].position,
+            &left_hand.predicted().landmarks[Wrist].position,
+            &left_hand.predicted().landmarks[MiddleFingerMcp].position,
+            &left_hand.predicted().landmarks[ThumbCmc].position,
+            &left_hand.predicted().landmarks[PinkyMcp].position,
         );
         rotate_g(&left_palm, l_palm_r, "left_palm", &mut mut_transform_q)?;
     }
@@ -225,10 +230,10 @@ pub fn rotate_hands(
     {
         let mcp: Option<Entity> = right_hand_parts.thumb.mcp;
         let mcp_r = compute_mcp_rotation_thumb(
-            &right_hand.get().landmarks[ThumbMcp].position,
-            &right_hand.get().landmarks[ThumbMcp].position,
-            &right_hand.get().landmarks[ThumbIp].position,
-            &right_hand.get().landmarks[IndexFingerMcp].position,
+            &right_hand.predicted().landmarks[ThumbMcp].position,
+            &right_hand.predicted().landmarks[ThumbMcp].position,
+            &right_hand.predicted().landmarks[ThumbIp].position,
+            &right_hand.predicted().landmarks[IndexFingerMcp].position,
         ) * Quat::from_rotation_x(-PI);
         rotate_g(&mcp, mcp_r, "thumb_mcp", &mut mut_transform_q)?;
     }
@@ -236,10 +241,10 @@ pub fn rotate_hands(
     {
         let ip = right_hand_parts.thumb.ip;
         let ip_r = compute_dip_rotation_thumb(
-            &right_hand.get().landmarks[ThumbMcp].position,
-            &right_hand.get().landmarks[ThumbIp].position,
-            &right_hand.get().landmarks[ThumbTip].position,
-            &right_hand.get().landmarks[IndexFingerMcp].position,
+            &right_hand.predicted().landmarks[ThumbMcp].position,
+            &right_hand.predicted().landmarks[ThumbIp].position,
+            &right_hand.predicted().landmarks[ThumbTip].position,
+            &right_hand.predicted().landmarks[IndexFingerMcp].position,
         ) * Quat::from_rotation_x(PI / 2.);
         rotate_g(&ip, ip_r, "thumb_dip", &mut mut_transform_q)?;
     }
@@ -248,10 +253,10 @@ pub fn rotate_hands(
         let mcp = right_hand_parts.index.mcp;
         let mcp_r = if gui_state.rotate_index_cmp {
             compute_mcp_rotation(
-                &right_hand.get().landmarks[IndexFingerMcp].position,
-                &right_hand.get().landmarks[IndexFingerPip].position,
-                &right_hand.get().landmarks[IndexFingerDip].position,
-                &right_hand.get().landmarks[ThumbMcp].position,
+                &right_hand.predicted().landmarks[IndexFingerMcp].position,
+                &right_hand.predicted().landmarks[IndexFingerPip].position,
+                &right_hand.predicted().landmarks[IndexFingerDip].position,
+                &right_hand.predicted().landmarks[ThumbMcp].position,
             ) * Quat::from_rotation_y(-PI / 2.)
         } else {
             Quat::IDENTITY
@@ -262,10 +267,10 @@ pub fn rotate_hands(
     {
         let pip = right_hand_parts.index.pip;
         let pip_r = compute_pip_rotation(
-            &right_hand.get().landmarks[IndexFingerMcp].position,
-            &right_hand.get().landmarks[IndexFingerPip].position,
-            &right_hand.get().landmarks[IndexFingerDip].position,
-            &right_hand.get().landmarks[ThumbMcp].position,
+            &right_hand.predicted().landmarks[IndexFingerMcp].position,
+            &right_hand.predicted().landmarks[IndexFingerPip].position,
+            &right_hand.predicted().landmarks[IndexFingerDip].position,
+            &right_hand.predicted().landmarks[ThumbMcp].position,
         );
         rotate_g(&pip, pip_r, "index_pip", &mut mut_transform_q)?;
     }
@@ -273,10 +278,10 @@ pub fn rotate_hands(
     {
         let dip = right_hand_parts.index.dip;
         let dip_r = compute_dip_rotation(
-            &right_hand.get().landmarks[IndexFingerMcp].position,
-            &right_hand.get().landmarks[IndexFingerDip].position,
-            &right_hand.get().landmarks[IndexFingerTip].position,
-            &right_hand.get().landmarks[ThumbMcp].position,
+            &right_hand.predicted().landmarks[IndexFingerMcp].position,
+            &right_hand.predicted().landmarks[IndexFingerDip].position,
+            &right_hand.predicted().landmarks[IndexFingerTip].position,
+            &right_hand.predicted().landmarks[ThumbMcp].position,
         );
         rotate_g(&dip, dip_r, "index_dip", &mut mut_transform_q)?;
     }
@@ -284,10 +289,10 @@ pub fn rotate_hands(
     {
         let mcp = right_hand_parts.middle.mcp;
         let mcp_r = compute_mcp_rotation(
-            &right_hand.get().landmarks[MiddleFingerMcp].position,
-            &right_hand.get().landmarks[MiddleFingerPip].position,
-            &right_hand.get().landmarks[MiddleFingerDip].position,
-            &right_hand.get().landmarks[IndexFingerMcp].position,
+            &right_hand.predicted().landmarks[MiddleFingerMcp].position,
+            &right_hand.predicted().landmarks[MiddleFingerPip].position,
+            &right_hand.predicted().landmarks[MiddleFingerDip].position,
+            &right_hand.predicted().landmarks[IndexFingerMcp].position,
         ) * Quat::from_rotation_y(-PI / 2.);
         rotate_g(&mcp, mcp_r, "middle_mcp", &mut mut_transform_q)?;
     }
@@ -295,10 +300,10 @@ pub fn rotate_hands(
     {
         let pip = right_hand_parts.middle.pip;
         let pip_r = compute_pip_rotation(
-            &right_hand.get().landmarks[MiddleFingerMcp].position,
-            &right_hand.get().landmarks[MiddleFingerPip].position,
-            &right_hand.get().landmarks[MiddleFingerDip].position,
-            &right_hand.get().landmarks[IndexFingerMcp].position,
+            &right_hand.predicted().landmarks[MiddleFingerMcp].position,
+            &right_hand.predicted().landmarks[MiddleFingerPip].position,
+            &right_hand.predicted().landmarks[MiddleFingerDip].position,
+            &right_hand.predicted().landmarks[IndexFingerMcp].position,
         );
         rotate_g(&pip, pip_r, "middle_pip", &mut mut_transform_q)?;
     }
@@ -307,10 +312,10 @@ pub fn rotate_hands(
         let dip = right_hand_parts.middle.dip;
 
         let dip_r = compute_dip_rotation(
-            &right_hand.get().landmarks[MiddleFingerMcp].position,
-            &right_hand.get().landmarks[MiddleFingerDip].position,
-            &right_hand.get().landmarks[MiddleFingerTip].position,
-            &right_hand.get().landmarks[IndexFingerMcp].position,
+            &right_hand.predicted().landmarks[MiddleFingerMcp].position,
+            &right_hand.predicted().landmarks[MiddleFingerDip].position,
+            &right_hand.predicted().landmarks[MiddleFingerTip].position,
+            &right_hand.predicted().landmarks[IndexFingerMcp].position,
         );
         rotate_g(&dip, dip_r, "middle_dip", &mut mut_transform_q)?;
     }
@@ -318,10 +323,10 @@ pub fn rotate_hands(
     {
         let mcp = right_hand_parts.ring.mcp;
         let mcp_r = compute_mcp_rotation(
-            &right_hand.get().landmarks[RingFingerMcp].position,
-            &right_hand.get().landmarks[RingFingerPip].position,
-            &right_hand.get().landmarks[RingFingerDip].position,
-            &right_hand.get().landmarks[MiddleFingerMcp].position,
+            &right_hand.predicted().landmarks[RingFingerMcp].position,
+            &right_hand.predicted().landmarks[RingFingerPip].position,
+            &right_hand.predicted().landmarks[RingFingerDip].position,
+            &right_hand.predicted().landmarks[MiddleFingerMcp].position,
         ) * Quat::from_rotation_y(-PI / 2.);
         rotate_g(&mcp, mcp_r, "ring_mcp", &mut mut_transform_q)?;
     }
@@ -329,10 +334,10 @@ pub fn rotate_hands(
     {
         let pip = right_hand_parts.ring.pip;
         let pip_r = compute_pip_rotation(
-            &right_hand.get().landmarks[RingFingerMcp].position,
-            &right_hand.get().landmarks[RingFingerPip].position,
-            &right_hand.get().landmarks[RingFingerDip].position,
-            &right_hand.get().landmarks[MiddleFingerMcp].position,
+            &right_hand.predicted().landmarks[RingFingerMcp].position,
+            &right_hand.predicted().landmarks[RingFingerPip].position,
+            &right_hand.predicted().landmarks[RingFingerDip].position,
+            &right_hand.predicted().landmarks[MiddleFingerMcp].position,
         ) * Quat::from_rotation_y(-PI / 2.);
         rotate_g(&pip, pip_r, "ring_pip", &mut mut_transform_q)?;
     }
@@ -340,10 +345,10 @@ pub fn rotate_hands(
     {
         let dip = right_hand_parts.ring.dip;
         let dip_r = compute_dip_rotation(
-            &right_hand.get().landmarks[RingFingerMcp].position,
-            &right_hand.get().landmarks[RingFingerDip].position,
-            &right_hand.get().landmarks[RingFingerTip].position,
-            &right_hand.get().landmarks[MiddleFingerMcp].position,
+            &right_hand.predicted().landmarks[RingFingerMcp].position,
+            &right_hand.predicted().landmarks[RingFingerDip].position,
+            &right_hand.predicted().landmarks[RingFingerTip].position,
+            &right_hand.predicted().landmarks[MiddleFingerMcp].position,
         ) * Quat::from_rotation_y(-PI / 2.);
         rotate_g(&dip, dip_r, "ring_dip", &mut mut_transform_q)?;
     }
@@ -351,10 +356,10 @@ pub fn rotate_hands(
     {
         let mcp = right_hand_parts.pinky.mcp;
         let mcp_r = compute_mcp_rotation(
-            &right_hand.get().landmarks[PinkyMcp].position,
-            &right_hand.get().landmarks[PinkyPip].position,
-            &right_hand.get().landmarks[PinkyDip].position,
-            &right_hand.get().landmarks[RingFingerMcp].position,
+            &right_hand.predicted().landmarks[PinkyMcp].position,
+            &right_hand.predicted().landmarks[PinkyPip].position,
+            &right_hand.predicted().landmarks[PinkyDip].position,
+            &right_hand.predicted().landmarks[RingFingerMcp].position,
         ) * Quat::from_rotation_y(-PI / 2.);
         rotate_g(&mcp, mcp_r, "pinky_mcp", &mut mut_transform_q)?;
     }
@@ -362,10 +367,10 @@ pub fn rotate_hands(
     {
         let pip = right_hand_parts.pinky.pip;
         let pip_r = compute_pip_rotation(
-            &right_hand.get().landmarks[PinkyMcp].position,
-            &right_hand.get().landmarks[PinkyPip].position,
-            &right_hand.get().landmarks[PinkyDip].position,
-            &right_hand.get().landmarks[RingFingerMcp].position,
+            &right_hand.predicted().landmarks[PinkyMcp].position,
+            &right_hand.predicted().landmarks[PinkyPip].position,
+            &right_hand.predicted().landmarks[PinkyDip].position,
+            &right_hand.predicted().landmarks[RingFingerMcp].position,
         ) * Quat::from_rotation_y(-PI / 2.);
         rotate_g(&pip, pip_r, "pinky_pip", &mut mut_transform_q)?;
     }
@@ -373,10 +378,10 @@ pub fn rotate_hands(
     {
         let dip = right_hand_parts.pinky.dip;
         let dip_r = compute_dip_rotation(
-            &right_hand.get().landmarks[PinkyMcp].position,
-            &right_hand.get().landmarks[PinkyDip].position,
-            &right_hand.get().landmarks[PinkyTip].position,
-            &right_hand.get().landmarks[RingFingerMcp].position,
+            &right_hand.predicted().landmarks[PinkyMcp].position,
+            &right_hand.predicted().landmarks[PinkyDip].position,
+            &right_hand.predicted().landmarks[PinkyTip].position,
+            &right_hand.predicted().landmarks[RingFingerMcp].position,
         ) * Quat::from_rotation_y(-PI / 2.);
         rotate_g(&dip, dip_r, "pinky_dip", &mut mut_transform_q)?;
     }
@@ -384,10 +389,10 @@ pub fn rotate_hands(
     {
         let right_palm = right_hand_parts.wrist;
         let r_palm_r = compute_right_palm_rotation(
-            &right_hand.get().landmarks[Wrist].position,
-            &right_hand.get().landmarks[MiddleFingerMcp].position,
-            &right_hand.get().landmarks[ThumbCmc].position,
-            &right_hand.get().landmarks[PinkyMcp].position,
+            &right_hand.predicted().landmarks[Wrist].position,
+            &right_hand.predicted().landmarks[MiddleFingerMcp].position,
+            &right_hand.predicted().landmarks[ThumbCmc].position,
+            &right_hand.predicted().landmarks[PinkyMcp].position,
         );
         rotate_g(&right_palm, r_palm_r, "right_palm", &mut mut_transform_q)?;
     }
@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+
+use crate::character_control::character_controller::CharacterParts;
+use crate::character_control::pose::{
+    PoseLandmarks, compute_chest_rotation, compute_pelvis_rotation, compute_root_rotation,
+};
+use crate::ui::state::GuiState;
+
+/// The hips followed by the spine bones, from the bottom up.
+pub const SPINE_LENGTH: usize = 4;
+
+/// The share of the torso's bend and twist each of the hips, spine, chest and upper chest takes,
+/// scaled to add up to one over the bones the model has. Without spine bones the hips take all of
+/// it.
+pub fn spine_weights(parts: &CharacterParts, gui_state: &GuiState) -> [f32; SPINE_LENGTH] {
+    let weight = |bone: Option<Entity>, weight: f32| if bone.is_some() { weight } else { 0.0 };
+    let weights = [
+        gui_state.hips_weight,
+        weight(parts.spine.spine, gui_state.spine_weight),
+        weight(parts.spine.chest, gui_state.chest_weight),
+        weight(parts.spine.upper_chest, gui_state.upper_chest_weight),
+    ];
+
+    let total: f32 = weights.iter().sum();
+    if total > 0.0 {
+        weights.map(|weight| weight / total)
+    } else {
+        [1.0, 0.0, 0.0, 0.0]
+    }
+}
+
+/// Frames of the hips and the spine bones, bending and twisting step by step from the upright
+/// pelvis to the shoulders by `weights`, see [`spine_weights`].
+///
+/// The hips only take their share of the bend, the twist between the hips and the shoulders is
+/// left to the spine. Once the weights add up to one the last frame is the one of the shoulders,
+/// and hips without a spine get [`compute_root_rotation`].
+pub fn spine_frames(pose: &PoseLandmarks, weights: [f32; SPINE_LENGTH]) -> [Quat; SPINE_LENGTH] {
+    let pelvis = compute_pelvis_rotation(pose);
+    let root = compute_root_rotation(pose);
+    let bend = root * pelvis.inverse();
+    let twist = compute_chest_rotation(pose) * root.inverse();
+
+    let mut reached = 0.0;
+    let mut frames = [Quat::IDENTITY; SPINE_LENGTH];
+    for (i, weight) in weights.into_iter().enumerate() {
+        reached += weight;
+        let bend = Quat::IDENTITY.slerp(bend, reached);
+        frames[i] = if i == 0 {
+            bend * pelvis
+        } else {
+            Quat::IDENTITY.slerp(twist, reached) * bend * pelvis
+        };
+    }
+    frames
+}
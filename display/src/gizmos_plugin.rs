@@ -15,6 +15,7 @@ use crate::{
 use bevy_simple_subsecond_system::prelude::*;
 
 use crate::api::pose_api::CurrentPose;
+use crate::math::filter::SmoothingFilter;
 use bevy::color::palettes::css::*;
 use std::f32::consts::PI;
 
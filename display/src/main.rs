@@ -1,13 +1,19 @@
 mod api;
+mod bvh;
 mod camera_controller;
 mod gizmos_plugin;
 mod ui;
-use std::{env, f32::consts::PI, path::PathBuf};
+use std::{env, f32::consts::PI, path::PathBuf, time::Duration};
 
 use api::api_server::MocapApiPlugin;
+use bvh::player::BvhPlayerPlugin;
 mod character_control;
 mod math;
 mod model_plugin;
+mod session;
+mod take;
+mod vmc;
+mod vrm;
 use crate::{
     material::moebius_material::MoebiusMaterialPlugin,
     material::post_processing_moebius::MoebiusPostProcessPlugin,
@@ -16,20 +22,29 @@ use crate::{
 };
 use bevy::{
     DefaultPlugins,
-    app::{App, Startup},
+    app::{App, ScheduleRunnerPlugin, Startup},
     ecs::system::Commands,
     pbr::PointLight,
     prelude::*,
+    render::{RenderPlugin, settings::WgpuSettings},
     scene::SceneInstanceReady,
     transform::components::Transform,
     utils::default,
+    window::ExitCondition,
+    winit::WinitPlugin,
 };
 use bevy_simple_subsecond_system::prelude::*;
 use camera_controller::CameraControllerPlugin;
 use character_control::character_controller::CharacterControllerPlugin;
 use character_control::mouth::MouthControlPlugin;
 use gizmos_plugin::GizmosPlugin;
+use session::playback::SessionPlaybackPlugin;
+use session::recorder::SessionRecorderPlugin;
+use take::recorder::TakeRecorderPlugin;
+use ui::state::GuiState;
 use ui::ui_controller::GuiControllerPlugin;
+use vmc::receiver::VmcReceiverPlugin;
+use vmc::sender::VmcSenderPlugin;
 mod material;
 // mod post_processing_plugin;
 mod shader_plugin;
@@ -43,27 +58,116 @@ fn main() {
         println!("CARGO_MANIFEST_DIR: {manifest_dir}");
     }
 
-    App::new()
-        .add_plugins((
+    let headless = env::args().any(|arg| arg == "--headless");
+    // `--model models/avatar.vrm` shows another avatar, relative to the `assets` directory.
+    let model = arg_value("--model")
+        .map(|path| ModelPlugin { path })
+        .unwrap_or_default();
+    // `--bvh ~/clips/walk.bvh` plays a BVH clip from anywhere, the timeline only lists the
+    // recordings directory.
+    let bvh_player = BvhPlayerPlugin {
+        clip: arg_value("--bvh").map(PathBuf::from),
+    };
+
+    let mut app = App::new();
+    if headless {
+        app.add_plugins(headless_plugins());
+    } else {
+        app.add_plugins((
             DefaultPlugins,
             // WorldInspectorPlugin::new(),
-            SimpleSubsecondPlugin::default(),
-            ModelPlugin,
-            MocapApiPlugin,
             GuiControllerPlugin,
             CameraControllerPlugin,
-            CharacterControllerPlugin,
             MoebiusMaterialPlugin,
             MoebiusPostProcessPlugin,
             GizmosPlugin,
             MouthControlPlugin,
         ))
-        .add_event::<SceneInstanceReady>()
         .add_systems(Startup, setup)
-        .add_systems(Update, update_lights)
+        .add_systems(Update, update_lights);
+    }
+
+    app.init_resource::<GuiState>();
+    apply_args(&mut app.world_mut().resource_mut::<GuiState>());
+    app.add_plugins((
+            SimpleSubsecondPlugin::default(),
+            model,
+            MocapApiPlugin,
+            CharacterControllerPlugin,
+            (VmcReceiverPlugin, VmcSenderPlugin),
+            (
+                SessionRecorderPlugin,
+                SessionPlaybackPlugin,
+                TakeRecorderPlugin,
+                bvh_player,
+            ),
+        ))
+        .add_event::<SceneInstanceReady>()
         // .add_observer(observer)
         .run();
 }
+
+/// The value following `name` on the command line.
+fn arg_value(name: &str) -> Option<String> {
+    env::args().skip_while(|arg| arg != name).nth(1)
+}
+
+/// Settings given on the command line, which the settings window starts from.
+fn apply_args(gui_state: &mut GuiState) {
+    // `--vmc-port 39539` listens for a VMC performer on another port.
+    if let Some(port) = arg_value("--vmc-port") {
+        match port.parse() {
+            Ok(port) => gui_state.vmc_receive_port = port,
+            Err(err) => error!("Invalid --vmc-port {}: {}", port, err),
+        }
+    }
+    // `--vmc-send 192.168.0.2:39540` sends the avatar to another VMC application.
+    if let Some(target) = arg_value("--vmc-send") {
+        match target
+            .rsplit_once(':')
+            .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+        {
+            Some((host, port)) => {
+                gui_state.vmc_send = true;
+                gui_state.vmc_send_host = host.to_string();
+                gui_state.vmc_send_port = port;
+            }
+            None => error!("Invalid --vmc-send {}, expected host:port", target),
+        }
+    }
+    if let Some(rate) = arg_value("--vmc-send-rate") {
+        match rate.parse() {
+            Ok(rate) => gui_state.vmc_send_rate = rate,
+            Err(err) => error!("Invalid --vmc-send-rate {}: {}", rate, err),
+        }
+    }
+}
+
+/// Everything ingest, filtering and retargeting need, without a window or a GPU.
+///
+/// The render plugins stay so the glTF loader still produces meshes, materials and the scene
+/// the bones are resolved from, they just never initialize a renderer.
+fn headless_plugins() -> impl PluginGroup {
+    DefaultPlugins
+        .set(WindowPlugin {
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            ..default()
+        })
+        .set(RenderPlugin {
+            render_creation: WgpuSettings {
+                backends: None,
+                ..default()
+            }
+            .into(),
+            ..default()
+        })
+        .disable::<WinitPlugin>()
+        .add(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+            1.0 / 60.0,
+        )))
+}
+
 #[derive(Component)]
 struct NeckTag;
 
@@ -0,0 +1,97 @@
+use bevy::math::{Mat3, Quat, Vec3};
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+/// A signed axis of Bevy's space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Reflect)]
+pub enum Axis {
+    X,
+    NegX,
+    Y,
+    NegY,
+    Z,
+    NegZ,
+}
+
+impl Axis {
+    pub fn unit(self) -> Vec3 {
+        match self {
+            Axis::X => Vec3::X,
+            Axis::NegX => Vec3::NEG_X,
+            Axis::Y => Vec3::Y,
+            Axis::NegY => Vec3::NEG_Y,
+            Axis::Z => Vec3::Z,
+            Axis::NegZ => Vec3::NEG_Z,
+        }
+    }
+}
+
+/// How an input source lays out its coordinates, used to bring them into Bevy's Y-up
+/// right-handed space, in metres.
+///
+/// Trackers and cameras differ only in their frame, so everything past the conversion (filters,
+/// rotations, retargeting) works the same for all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Reflect)]
+pub struct CoordinateFrame {
+    /// Where the source x, y and z axes point in Bevy's space. An odd number of flips or swaps
+    /// makes the source left-handed.
+    pub axes: [Axis; 3],
+    /// Source units in a metre, e.g. `100` for centimetres.
+    pub units_per_metre: f32,
+    /// Swaps left and right after the axes are mapped, for cameras that show a mirrored image.
+    pub mirror: bool,
+}
+
+impl CoordinateFrame {
+    /// MediaPipe landmarks: x to the right of the image, y down and z away from the camera.
+    pub const MEDIAPIPE: Self = Self {
+        axes: [Axis::NegX, Axis::NegY, Axis::Z],
+        units_per_metre: 1.0,
+        mirror: false,
+    };
+
+    /// Unity, as used by VMC: left-handed and Y-up, with X mirrored compared to Bevy.
+    pub const UNITY: Self = Self {
+        axes: [Axis::NegX, Axis::Y, Axis::Z],
+        units_per_metre: 1.0,
+        mirror: false,
+    };
+
+    /// Maps source directions onto Bevy directions.
+    fn basis(&self) -> Mat3 {
+        let basis = Mat3::from_cols(
+            self.axes[0].unit(),
+            self.axes[1].unit(),
+            self.axes[2].unit(),
+        );
+        if self.mirror {
+            Mat3::from_diagonal(Vec3::new(-1.0, 1.0, 1.0)) * basis
+        } else {
+            basis
+        }
+    }
+
+    /// Converts a source position into Bevy's space.
+    pub fn position(&self, position: Vec3) -> Vec3 {
+        self.basis() * position / self.units_per_metre
+    }
+
+    /// Converts a source rotation into Bevy's space, also across a change of handedness.
+    pub fn rotation(&self, rotation: Quat) -> Quat {
+        let basis = self.basis();
+        Quat::from_mat3(&(basis * Mat3::from_quat(rotation) * basis.transpose())).normalize()
+    }
+
+    /// Converts a Bevy position back into the source space, the inverse of
+    /// [`CoordinateFrame::position`].
+    pub fn to_source_position(&self, position: Vec3) -> Vec3 {
+        self.basis().transpose() * position * self.units_per_metre
+    }
+
+    /// Converts a Bevy rotation back into the source space, the inverse of
+    /// [`CoordinateFrame::rotation`].
+    pub fn to_source_rotation(&self, rotation: Quat) -> Quat {
+        let basis = self.basis();
+        Quat::from_mat3(&(basis.transpose() * Mat3::from_quat(rotation) * basis)).normalize()
+    }
+}
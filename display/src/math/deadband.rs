@@ -0,0 +1,51 @@
+use std::ops::{Add, Mul, Sub};
+
+use serde::{Deserialize, Serialize};
+
+use crate::math::filter::{Magnitude, SmoothingFilter};
+
+/// Holds the estimate until a measurement moves further than `threshold` from it, then follows
+/// the measurement at that distance, so small tremors are dropped without a jump when they end.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Deadband<T> {
+    pub position: T,
+    pub threshold: f32,
+}
+
+impl<T> Deadband<T> {
+    pub fn new(position: T, threshold: f32) -> Self {
+        Self {
+            position,
+            threshold,
+        }
+    }
+
+    pub fn set_threshold(&mut self, threshold: f32) -> &mut Self {
+        self.threshold = threshold;
+        self
+    }
+}
+
+impl<T> SmoothingFilter<T> for Deadband<T>
+where
+    for<'a> &'a T: Add<&'a T, Output = T> + Sub<&'a T, Output = T> + Mul<f32, Output = T>,
+    T: Magnitude,
+{
+    fn update(&mut self, measured_pos: &T, _dt: f32) -> &T {
+        let difference = measured_pos - &self.position;
+        let distance = difference.magnitude();
+        if distance > self.threshold {
+            let step = (distance - self.threshold) / distance;
+            self.position = &self.position + &(&difference * step);
+        }
+        &self.position
+    }
+
+    fn get(&self) -> &T {
+        &self.position
+    }
+
+    fn reset(&mut self, position: T) {
+        self.position = position;
+    }
+}
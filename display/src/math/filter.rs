@@ -0,0 +1,361 @@
+use std::ops::{Add, Mul, Sub};
+use std::time::Instant;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::math::deadband::Deadband;
+use crate::math::kalman_filter::VelocityKalman;
+use crate::math::one_euro_filter::OneEuroFilter;
+use crate::math::outlier_gate::OutlierGate;
+
+/// Size of a difference between two values, used by the filters as the speed or distance of
+/// change.
+pub trait Magnitude {
+    fn magnitude(&self) -> f32;
+}
+
+/// Values made of separately measured elements, such as the landmarks of a pose, which
+/// [`VelocityKalman`] tracks each with their own covariance.
+pub trait Elements: Sized {
+    fn element_count(&self) -> usize;
+
+    /// How far the measurement of element `i` can be trusted, from `0` to `1`.
+    fn confidence(&self, _i: usize) -> f32 {
+        1.0
+    }
+
+    /// Size of element `i`, which [`OneEuroFilter`] adapts the cutoff of each element to.
+    fn element_magnitude(&self, i: usize) -> f32;
+
+    /// Multiplies every element by its own factor.
+    fn scale_elements(&self, factors: &[f32]) -> Self;
+}
+
+/// A filter that turns noisy measurements of `T` into a smoothed estimate.
+pub trait SmoothingFilter<T> {
+    /// Feeds a measurement taken `dt` seconds after the previous one and returns the estimate.
+    fn update(&mut self, measured_pos: &T, dt: f32) -> &T;
+
+    fn get(&self) -> &T;
+
+    /// Restarts the filter at `position`, forgetting everything it has seen.
+    fn reset(&mut self, position: T);
+
+    /// Estimated rate of change per second, for filters that track one.
+    fn velocity(&self) -> Option<&T> {
+        None
+    }
+}
+
+/// Which smoother a stream uses, selectable at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect, Serialize, Deserialize)]
+pub enum FilterKind {
+    /// Measurements are used as they arrive.
+    Off,
+    #[default]
+    Kalman,
+    OneEuro,
+}
+
+/// How the filter chain of a stream is put together, see [`GuiState`].
+///
+/// [`GuiState`]: crate::ui::state::GuiState
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilterConfig {
+    /// Measurements further than this from the predicted value are rejected before smoothing,
+    /// `0` leaves the gate out.
+    pub gate: f32,
+    pub kind: FilterKind,
+    /// Process noise of the Kalman position and velocity, how far they may drift per frame.
+    pub position_noise: f32,
+    pub velocity_noise: f32,
+    /// Kalman measurement noise of a fully visible landmark, higher smooths more.
+    pub measurement_noise: f32,
+    pub min_cutoff: f32,
+    pub beta: f32,
+    /// Changes smaller than this are held back after smoothing, `0` leaves the deadband out.
+    pub deadband: f32,
+}
+
+impl FilterConfig {
+    /// The stages of the chain, in the order measurements pass through them.
+    ///
+    /// Checked against the chain every frame, so it is built without allocating.
+    fn layout(&self) -> impl Iterator<Item = StageKind> + Clone {
+        let smoother = match self.kind {
+            FilterKind::Off => None,
+            FilterKind::Kalman => Some(StageKind::Kalman),
+            FilterKind::OneEuro => Some(StageKind::OneEuro),
+        };
+        [
+            (self.gate > 0.0).then_some(StageKind::Gate),
+            smoother,
+            (self.deadband > 0.0).then_some(StageKind::Deadband),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// How far past its last measurement the character systems read a stream, see
+/// [`FilterChain::predict`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PredictionConfig {
+    /// Added on top of the time since the last measurement, to make up for the capture latency.
+    pub look_ahead: f32,
+    /// Longest extrapolation in seconds, so a stalled stream does not drift off.
+    pub max_extrapolation: f32,
+}
+
+/// After a gap this long, in seconds, a chain restarts at the next measurement instead of
+/// extrapolating the stale estimate across it.
+const MAX_GAP: f32 = 0.5;
+
+/// The One Euro velocity estimate is smoothed at a fixed 1 Hz, as recommended by its authors.
+const ONE_EURO_D_CUTOFF: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StageKind {
+    Gate,
+    Kalman,
+    OneEuro,
+    Deadband,
+}
+
+/// One stage of a [`FilterChain`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum FilterStage<T> {
+    Gate(OutlierGate<T>),
+    Kalman(VelocityKalman<T>),
+    OneEuro(OneEuroFilter<T>),
+    Deadband(Deadband<T>),
+}
+
+impl<T> FilterStage<T>
+where
+    for<'a> &'a T: Sub<&'a T, Output = T>,
+    T: Clone + Elements,
+{
+    fn new(kind: StageKind, position: T, config: &FilterConfig) -> Self {
+        match kind {
+            StageKind::Gate => FilterStage::Gate(OutlierGate::new(position, config.gate)),
+            StageKind::Kalman => FilterStage::Kalman(VelocityKalman::with_noises(
+                position,
+                config.position_noise,
+                config.velocity_noise,
+                config.measurement_noise,
+            )),
+            StageKind::OneEuro => FilterStage::OneEuro(OneEuroFilter::with_params(
+                position,
+                config.min_cutoff,
+                config.beta,
+                ONE_EURO_D_CUTOFF,
+            )),
+            StageKind::Deadband => FilterStage::Deadband(Deadband::new(position, config.deadband)),
+        }
+    }
+}
+
+impl<T> FilterStage<T> {
+    fn kind(&self) -> StageKind {
+        match self {
+            FilterStage::Gate(_) => StageKind::Gate,
+            FilterStage::Kalman(_) => StageKind::Kalman,
+            FilterStage::OneEuro(_) => StageKind::OneEuro,
+            FilterStage::Deadband(_) => StageKind::Deadband,
+        }
+    }
+
+    fn set_params(&mut self, config: &FilterConfig) {
+        match self {
+            FilterStage::Gate(filter) => {
+                filter.set_threshold(config.gate);
+            }
+            FilterStage::Kalman(filter) => {
+                filter
+                    .set_position_noise(config.position_noise)
+                    .set_velocity_noise(config.velocity_noise)
+                    .set_measurement_noise(config.measurement_noise);
+            }
+            FilterStage::OneEuro(filter) => {
+                filter
+                    .set_min_cutoff(config.min_cutoff)
+                    .set_beta(config.beta);
+            }
+            FilterStage::Deadband(filter) => {
+                filter.set_threshold(config.deadband);
+            }
+        }
+    }
+}
+
+impl<T> SmoothingFilter<T> for FilterStage<T>
+where
+    for<'a> &'a T: Add<&'a T, Output = T> + Sub<&'a T, Output = T> + Mul<f32, Output = T>,
+    T: Clone + Magnitude + Elements,
+{
+    fn update(&mut self, measured_pos: &T, dt: f32) -> &T {
+        match self {
+            FilterStage::Gate(filter) => filter.update(measured_pos, dt),
+            FilterStage::Kalman(filter) => filter.update(measured_pos, dt),
+            FilterStage::OneEuro(filter) => filter.update(measured_pos, dt),
+            FilterStage::Deadband(filter) => filter.update(measured_pos, dt),
+        }
+    }
+
+    fn get(&self) -> &T {
+        match self {
+            FilterStage::Gate(filter) => filter.get(),
+            FilterStage::Kalman(filter) => filter.get(),
+            FilterStage::OneEuro(filter) => filter.get(),
+            FilterStage::Deadband(filter) => filter.get(),
+        }
+    }
+
+    fn reset(&mut self, position: T) {
+        match self {
+            FilterStage::Gate(filter) => filter.reset(position),
+            FilterStage::Kalman(filter) => filter.reset(position),
+            FilterStage::OneEuro(filter) => filter.reset(position),
+            FilterStage::Deadband(filter) => filter.reset(position),
+        }
+    }
+
+    fn velocity(&self) -> Option<&T> {
+        match self {
+            // The gate velocity is the raw difference of two measurements, too noisy to predict with
+            FilterStage::Gate(_) => None,
+            FilterStage::Kalman(filter) => filter.velocity(),
+            FilterStage::OneEuro(filter) => filter.velocity(),
+            FilterStage::Deadband(filter) => filter.velocity(),
+        }
+    }
+}
+
+/// Measurements pass through each stage in turn, the last stage gives the estimate.
+///
+/// Without stages, the estimate is the last measurement.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FilterChain<T> {
+    pub stages: Vec<FilterStage<T>>,
+    output: T,
+    /// When the last measurement was applied.
+    #[serde(skip)]
+    updated_at: Option<Instant>,
+    /// The estimate extrapolated by [`FilterChain::predict`].
+    // A default path keeps serde from requiring `T: Default`
+    #[serde(skip, default = "Option::default")]
+    predicted: Option<T>,
+}
+
+impl<T> FilterChain<T>
+where
+    for<'a> &'a T: Sub<&'a T, Output = T>,
+    T: Clone + Elements,
+{
+    pub fn new(position: T, config: &FilterConfig) -> Self {
+        let mut chain = Self::from_stages(position, Vec::new());
+        chain.configure(config);
+        chain
+    }
+
+    pub fn from_stages(position: T, stages: Vec<FilterStage<T>>) -> Self {
+        Self {
+            stages,
+            output: position,
+            updated_at: Some(Instant::now()),
+            predicted: None,
+        }
+    }
+
+    /// The estimate as last extrapolated by [`FilterChain::predict`], or the estimate itself.
+    pub fn predicted(&self) -> &T {
+        self.predicted.as_ref().unwrap_or(&self.output)
+    }
+
+    /// Measurements rejected by the outlier gate of the chain, if it has one.
+    pub fn rejected_frames(&self) -> u64 {
+        self.stages
+            .iter()
+            .map(|stage| match stage {
+                FilterStage::Gate(gate) => gate.rejected,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Rebuilds the chain from the current estimate if its stages changed, and applies the
+    /// parameters. Stages that stay keep their state.
+    pub fn configure(&mut self, config: &FilterConfig) {
+        let layout = config.layout();
+        if !self.stages.iter().map(FilterStage::kind).eq(layout.clone()) {
+            self.stages = layout
+                .map(|kind| FilterStage::new(kind, self.output.clone(), config))
+                .collect();
+        }
+        for stage in self.stages.iter_mut() {
+            stage.set_params(config);
+        }
+    }
+}
+
+impl<T> SmoothingFilter<T> for FilterChain<T>
+where
+    for<'a> &'a T: Add<&'a T, Output = T> + Sub<&'a T, Output = T> + Mul<f32, Output = T>,
+    T: Clone + Magnitude + Elements,
+{
+    fn update(&mut self, measured_pos: &T, dt: f32) -> &T {
+        if dt > MAX_GAP {
+            self.reset(measured_pos.clone());
+            return &self.output;
+        }
+
+        let mut value = measured_pos.clone();
+        for stage in self.stages.iter_mut() {
+            value = stage.update(&value, dt).clone();
+        }
+        self.output = value;
+        self.updated_at = Some(Instant::now());
+        self.predicted = None;
+        &self.output
+    }
+
+    fn get(&self) -> &T {
+        &self.output
+    }
+
+    fn reset(&mut self, position: T) {
+        for stage in self.stages.iter_mut() {
+            stage.reset(position.clone());
+        }
+        self.output = position;
+        self.updated_at = Some(Instant::now());
+        self.predicted = None;
+    }
+
+    /// The velocity of the last stage tracking one, usually the smoother.
+    fn velocity(&self) -> Option<&T> {
+        self.stages.iter().rev().find_map(FilterStage::velocity)
+    }
+}
+
+impl<T> FilterChain<T>
+where
+    for<'a> &'a T: Add<&'a T, Output = T> + Sub<&'a T, Output = T> + Mul<f32, Output = T>,
+    T: Clone + Magnitude + Elements,
+{
+    /// Extrapolates the estimate along its velocity to `now` plus the look-ahead, so the render
+    /// frame rate is not tied to the capture rate and part of the capture latency is hidden.
+    ///
+    /// Chains without a velocity, such as with the filter off, keep the estimate as is.
+    pub fn predict(&mut self, now: Instant, config: &PredictionConfig) {
+        let elapsed = self.updated_at.map_or(0.0, |updated_at| {
+            now.saturating_duration_since(updated_at).as_secs_f32()
+        });
+        let ahead = (elapsed + config.look_ahead).clamp(0.0, config.max_extrapolation);
+        self.predicted = self
+            .velocity()
+            .map(|velocity| &self.output + &(velocity * ahead));
+    }
+}
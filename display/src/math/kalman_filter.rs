@@ -3,46 +3,48 @@ use std::ops::{Add, Mul, Sub};
 use bevy::math::*;
 use serde::{Deserialize, Serialize};
 
+use crate::math::filter::{Elements, SmoothingFilter};
+
+/// Measurements below this confidence are treated as if they had it, so the measurement noise
+/// of an occluded element stays finite.
+const MIN_CONFIDENCE: f32 = 0.01;
+
+// Initial covariance of each element
+const P_POS: f32 = 5.0;
+const P_POS_VEL: f32 = 0.0;
+const P_VEL: f32 = 5.0;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct VelocityKalman<T> {
     // x
     pub position: T, // Estimated position
     pub velocity: T, // Estimated velocity
 
-    // Covariance matrix P:
+    // Covariance matrix P of each element of T:
     // [ p_pos, p_pos_vel ]
     // [ p_pos_vel, p_vel ]
-    pub p_pos: f32,
-    pub p_pos_vel: f32,
-    pub p_vel: f32,
+    pub p_pos: Vec<f32>,
+    pub p_pos_vel: Vec<f32>,
+    pub p_vel: Vec<f32>,
 
     // Q Process noise variances
     pub position_noise: f32,
     pub velocity_noise: f32,
-    // R (measurement noise)
+    // R (measurement noise) of a fully confident measurement
     pub measurement_noise: f32,
+
+    // Kalman gains of the last update, kept so updating doesn't allocate
+    #[serde(skip)]
+    k_pos: Vec<f32>,
+    #[serde(skip)]
+    k_vel: Vec<f32>,
 }
 
 impl<T> VelocityKalman<T>
 where
     for<'a> &'a T: Sub<&'a T, Output = T>,
-    T: Clone,
+    T: Clone + Elements,
 {
-    pub fn new(position: T) -> Self {
-        let p = position.clone();
-        Self {
-            position,
-            velocity: (&p - &p),
-            position_noise: 1.,
-            velocity_noise: 3.,
-            measurement_noise: 100.,
-
-            p_pos: 5.0,
-            p_pos_vel: 0.0,
-            p_vel: 5.0,
-        }
-    }
-
     pub fn with_noises(
         position: T,
         position_noise: f32,
@@ -50,6 +52,7 @@ where
         measurement_noise: f32,
     ) -> Self {
         let p = position.clone();
+        let n = position.element_count();
         Self {
             position,
             velocity: (&p - &p),
@@ -57,63 +60,103 @@ where
             velocity_noise,
             measurement_noise,
 
-            p_pos: 5.0,
-            p_pos_vel: 0.0,
-            p_vel: 5.0,
+            p_pos: vec![P_POS; n],
+            p_pos_vel: vec![P_POS_VEL; n],
+            p_vel: vec![P_VEL; n],
+
+            k_pos: vec![0.0; n],
+            k_vel: vec![0.0; n],
         }
     }
 }
 
-impl<T> VelocityKalman<T>
+impl<T> SmoothingFilter<T> for VelocityKalman<T>
 where
     for<'a> &'a T: Add<&'a T, Output = T> + Sub<&'a T, Output = T> + Mul<f32, Output = T>,
+    T: Elements,
 {
-    pub fn update(&mut self, measured_pos: &T, dt: f32) -> &T {
+    fn update(&mut self, measured_pos: &T, dt: f32) -> &T {
         //// Prediction Step
         // State prediction
         let predicted_pos = &self.position + &(&self.velocity * dt);
         let predicted_vel = &self.velocity;
 
-        // Covariance prediction:
-        //
-        // P = A * P * A^T + Q
-        //
-        // A = [1 dt; 0 1]
-        //
-        let p_pos_new =
-            self.p_pos + 2.0 * dt * self.p_pos_vel + dt * dt * self.p_vel + self.position_noise;
-        let p_pos_vel_new = self.p_pos_vel + dt * self.p_vel;
-        let p_vel_new = self.p_vel + self.velocity_noise;
-
         //// Update Step
         // Innovation
         let innovation = measured_pos - &predicted_pos;
 
-        // Innovation covariance S
-        let s = p_pos_new + self.measurement_noise;
-
-        // Kalman gain
-        let k_pos = p_pos_new / s;
-        let k_vel = p_pos_vel_new / s;
+        let n = measured_pos.element_count();
+        self.p_pos.resize(n, P_POS);
+        self.p_pos_vel.resize(n, P_POS_VEL);
+        self.p_vel.resize(n, P_VEL);
+        self.k_pos.resize(n, 0.0);
+        self.k_vel.resize(n, 0.0);
+
+        for i in 0..n {
+            // Covariance prediction:
+            //
+            // P = A * P * A^T + Q
+            //
+            // A = [1 dt; 0 1]
+            //
+            let p_pos_new = self.p_pos[i]
+                + 2.0 * dt * self.p_pos_vel[i]
+                + dt * dt * self.p_vel[i]
+                + self.position_noise;
+            let p_pos_vel_new = self.p_pos_vel[i] + dt * self.p_vel[i];
+            let p_vel_new = self.p_vel[i] + self.velocity_noise;
+
+            // Innovation covariance S, low confidence measurements are mostly ignored so the
+            // element coasts on its predicted velocity
+            let confidence = measured_pos.confidence(i).max(MIN_CONFIDENCE);
+            let s = p_pos_new + self.measurement_noise / confidence;
+
+            // Kalman gain
+            let k_p = p_pos_new / s;
+            let k_v = p_pos_vel_new / s;
+
+            // Covariance update
+            self.p_pos[i] = (1.0 - k_p) * p_pos_new;
+            self.p_pos_vel[i] = (1.0 - k_p) * p_pos_vel_new;
+            self.p_vel[i] = p_vel_new - k_v * p_pos_vel_new;
+
+            self.k_pos[i] = k_p;
+            self.k_vel[i] = k_v;
+        }
 
         // State update
-        self.position = &predicted_pos + &(&innovation * k_pos);
-        self.velocity = predicted_vel + &(&innovation * k_vel);
-
-        // Covariance update
-        self.p_pos = (1.0 - k_pos) * p_pos_new;
-        self.p_pos_vel = (1.0 - k_pos) * p_pos_vel_new;
-        self.p_vel = p_vel_new - k_vel * p_pos_vel_new;
+        self.position = &predicted_pos + &innovation.scale_elements(&self.k_pos);
+        self.velocity = predicted_vel + &innovation.scale_elements(&self.k_vel);
 
         &self.position
     }
-}
 
-impl<T> VelocityKalman<T> {
-    pub fn get(&self) -> &T {
+    fn get(&self) -> &T {
         &self.position
     }
 
+    fn velocity(&self) -> Option<&T> {
+        Some(&self.velocity)
+    }
+
+    fn reset(&mut self, position: T) {
+        let n = position.element_count();
+        self.velocity = &position - &position;
+        self.position = position;
+
+        // Refilled in place, keeping the buffers
+        for (p, initial) in [
+            (&mut self.p_pos, P_POS),
+            (&mut self.p_pos_vel, P_POS_VEL),
+            (&mut self.p_vel, P_VEL),
+        ] {
+            p.clear();
+            p.resize(n, initial);
+        }
+    }
+}
+
+impl<T> VelocityKalman<T> {
     pub fn set_position_noise(&mut self, noise: f32) -> &mut Self {
         self.position_noise = noise;
         self
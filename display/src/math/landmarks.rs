@@ -1,7 +1,10 @@
 use crate::api::pose_api::LandmarkJson;
+use crate::math::coordinate_frame::CoordinateFrame;
+use crate::math::filter::{Elements, Magnitude};
 use bevy::math::Vec3;
 use serde::{Deserialize, Serialize};
-use std::ops::{Add, Mul, Sub};
+use std::marker::PhantomData;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
 use strum::EnumCount;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -60,19 +63,11 @@ impl Mul<f32> for &Landmark {
     }
 }
 
-impl From<LandmarkJson> for Landmark {
-    fn from(lj: LandmarkJson) -> Self {
+impl Landmark {
+    /// Converts a landmark from a source laid out as `frame` into Bevy's space.
+    pub fn from_json(lj: &LandmarkJson, frame: &CoordinateFrame) -> Self {
         Landmark {
-            position: Vec3::new(-lj.x, -lj.y, lj.z),
-            visibility: lj.visibility,
-        }
-    }
-}
-
-impl From<&LandmarkJson> for Landmark {
-    fn from(lj: &LandmarkJson) -> Self {
-        Landmark {
-            position: Vec3::new(-lj.x, -lj.y, lj.z),
+            position: frame.position(Vec3::new(lj.x, lj.y, lj.z)),
             visibility: lj.visibility,
         }
     }
@@ -82,53 +77,119 @@ pub trait LandmarkIndex: EnumCount {
     fn as_index(self) -> usize;
 }
 
+/// One landmark per variant of `Index`, stored inline so the filter arithmetic never allocates.
+///
+/// `N` has to be `Index::COUNT`, which stable Rust can't spell as a default, so it is given
+/// through aliases like `Landmarks<PoseLandmarkIndex, { PoseLandmarkIndex::COUNT }>`.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct Landmarks<Index: LandmarkIndex> {
-    pub data: Vec<Landmark>,
-    _phantom: std::marker::PhantomData<Index>,
+pub struct Landmarks<Index: LandmarkIndex, const N: usize> {
+    #[serde(with = "landmark_array")]
+    pub data: [Landmark; N],
+    #[serde(skip)]
+    _phantom: PhantomData<Index>,
 }
 
-impl<'a, Index: LandmarkIndex> Add for &'a Landmarks<Index> {
-    type Output = Landmarks<Index>;
+impl<Index: LandmarkIndex, const N: usize> Landmarks<Index, N> {
+    const SIZE_MATCHES_INDEX: () = assert!(N == Index::COUNT, "N must be Index::COUNT");
 
-    fn add(self, other: Self) -> Self::Output {
-        let mut new_data = Vec::with_capacity(self.data.len());
-        for i in 0..self.data.len() {
-            new_data.push(self.data[i] + other.data[i]);
+    pub fn from_fn(f: impl FnMut(usize) -> Landmark) -> Self {
+        let () = Self::SIZE_MATCHES_INDEX;
+        Self {
+            data: std::array::from_fn(f),
+            _phantom: PhantomData,
         }
-        Landmarks {
-            data: new_data,
-            _phantom: std::marker::PhantomData,
+    }
+
+    /// Converts the landmarks of a source laid out as `frame`, checking there is one per index.
+    pub fn from_json(
+        value: &[LandmarkJson],
+        frame: &CoordinateFrame,
+    ) -> Result<Self, LandmarksError> {
+        if value.len() != Index::COUNT {
+            return Err(LandmarksError::IncorrectLength {
+                expected: Index::COUNT,
+                actual: value.len(),
+            });
         }
+        Ok(Landmarks::from_fn(|i| {
+            Landmark::from_json(&value[i], frame)
+        }))
     }
 }
 
-impl<'a, Index: LandmarkIndex> Sub for &'a Landmarks<Index> {
-    type Output = Landmarks<Index>;
+impl<Index: LandmarkIndex, const N: usize> AddAssign<&Landmarks<Index, N>> for Landmarks<Index, N> {
+    fn add_assign(&mut self, rhs: &Self) {
+        for (landmark, other) in self.data.iter_mut().zip(&rhs.data) {
+            *landmark = *landmark + *other;
+        }
+    }
+}
 
-    fn sub(self, other: Self) -> Self::Output {
-        let mut new_data = Vec::with_capacity(self.data.len());
-        for i in 0..self.data.len() {
-            new_data.push(self.data[i] - other.data[i]);
+impl<Index: LandmarkIndex, const N: usize> SubAssign<&Landmarks<Index, N>> for Landmarks<Index, N> {
+    fn sub_assign(&mut self, rhs: &Self) {
+        for (landmark, other) in self.data.iter_mut().zip(&rhs.data) {
+            *landmark = *landmark - *other;
         }
-        Landmarks {
-            data: new_data,
-            _phantom: std::marker::PhantomData,
+    }
+}
+
+impl<Index: LandmarkIndex, const N: usize> MulAssign<f32> for Landmarks<Index, N> {
+    fn mul_assign(&mut self, rhs: f32) {
+        for landmark in self.data.iter_mut() {
+            *landmark = *landmark * rhs;
         }
     }
 }
 
-impl<'a, Index: LandmarkIndex> Mul<f32> for &'a Landmarks<Index> {
-    type Output = Landmarks<Index>;
+impl<'a, Index: LandmarkIndex, const N: usize> Add for &'a Landmarks<Index, N> {
+    type Output = Landmarks<Index, N>;
+
+    fn add(self, other: Self) -> Self::Output {
+        Landmarks::from_fn(|i| self.data[i] + other.data[i])
+    }
+}
+
+impl<'a, Index: LandmarkIndex, const N: usize> Sub for &'a Landmarks<Index, N> {
+    type Output = Landmarks<Index, N>;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Landmarks::from_fn(|i| self.data[i] - other.data[i])
+    }
+}
+
+impl<'a, Index: LandmarkIndex, const N: usize> Mul<f32> for &'a Landmarks<Index, N> {
+    type Output = Landmarks<Index, N>;
     fn mul(self, rhs: f32) -> Self::Output {
-        let mut new_data = Vec::with_capacity(self.data.len());
-        for i in 0..self.data.len() {
-            new_data.push(self.data[i] * rhs);
-        }
-        Landmarks {
-            data: new_data,
-            _phantom: std::marker::PhantomData,
-        }
+        Landmarks::from_fn(|i| self.data[i] * rhs)
+    }
+}
+
+/// The fastest landmark sets the speed, so a waving hand is not smoothed away by a still body.
+impl<Index: LandmarkIndex, const N: usize> Magnitude for Landmarks<Index, N> {
+    fn magnitude(&self) -> f32 {
+        self.data
+            .iter()
+            .map(|landmark| landmark.position.length())
+            .fold(0.0, f32::max)
+    }
+}
+
+/// Each landmark is an element, trusted as far as MediaPipe saw it.
+impl<Index: LandmarkIndex, const N: usize> Elements for Landmarks<Index, N> {
+    fn element_count(&self) -> usize {
+        N
+    }
+
+    fn confidence(&self, i: usize) -> f32 {
+        self.data[i].visibility.clamp(0.0, 1.0)
+    }
+
+    fn element_magnitude(&self, i: usize) -> f32 {
+        self.data[i].position.length()
+    }
+
+    fn scale_elements(&self, factors: &[f32]) -> Self {
+        Landmarks::from_fn(|i| self.data[i] * factors[i])
     }
 }
 
@@ -140,89 +201,59 @@ pub enum LandmarksError {
     NoKeyPoints,
 }
 
-impl<Index: LandmarkIndex> TryFrom<&[LandmarkJson]> for Landmarks<Index> {
-    type Error = LandmarksError;
-    fn try_from(value: &[LandmarkJson]) -> Result<Self, Self::Error> {
-        if value.len() != Index::COUNT {
-            return Err(LandmarksError::IncorrectLength {
-                expected: Index::COUNT,
-                actual: value.len(),
-            });
-        }
-        Ok(Landmarks {
-            data: value.into_iter().map(Landmark::from).collect::<Vec<_>>(),
-            _phantom: std::marker::PhantomData,
-        })
-    }
-}
+impl<Index: LandmarkIndex, const N: usize> std::ops::Index<Index> for Landmarks<Index, N> {
+    type Output = Landmark;
 
-impl<Index: LandmarkIndex> TryFrom<Vec<LandmarkJson>> for Landmarks<Index> {
-    type Error = LandmarksError;
-    fn try_from(value: Vec<LandmarkJson>) -> Result<Self, Self::Error> {
-        if value.len() != Index::COUNT {
-            return Err(LandmarksError::IncorrectLength {
-                expected: Index::COUNT,
-                actual: value.len(),
-            });
-        }
-        Ok(Landmarks {
-            data: value.into_iter().map(Landmark::from).collect::<Vec<_>>(),
-            _phantom: std::marker::PhantomData,
-        })
+    fn index(&self, index: Index) -> &Self::Output {
+        &self.data[index.as_index()]
     }
 }
 
-impl<Index: LandmarkIndex> TryFrom<&Vec<LandmarkJson>> for Landmarks<Index> {
-    type Error = LandmarksError;
-    fn try_from(value: &Vec<LandmarkJson>) -> Result<Self, Self::Error> {
-        if value.len() != Index::COUNT {
-            return Err(LandmarksError::IncorrectLength {
-                expected: Index::COUNT,
-                actual: value.len(),
-            });
-        }
-        Ok(Landmarks {
-            data: value.into_iter().map(Landmark::from).collect::<Vec<_>>(),
-            _phantom: std::marker::PhantomData,
-        })
+impl<Index: LandmarkIndex, const N: usize> std::ops::IndexMut<Index> for Landmarks<Index, N> {
+    fn index_mut(&mut self, index: Index) -> &mut Self::Output {
+        &mut self.data[index.as_index()]
     }
 }
 
-impl<Index: LandmarkIndex> std::ops::Index<Index> for Landmarks<Index> {
-    type Output = Landmark;
+impl<Index: LandmarkIndex, const N: usize> Add for Landmarks<Index, N> {
+    type Output = Self;
 
-    fn index(&self, index: Index) -> &Self::Output {
-        &self.data[index.as_index()]
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self += &rhs;
+        self
     }
 }
 
-impl<Index: LandmarkIndex> Add for Landmarks<Index> {
+impl<Index: LandmarkIndex, const N: usize> Mul<f32> for Landmarks<Index, N> {
     type Output = Self;
 
-    fn add(self, rhs: Self) -> Self::Output {
-        Self {
-            data: self
-                .data
-                .into_iter()
-                .zip(rhs.data.into_iter())
-                .map(|(left, right)| left + right)
-                .collect(),
-            _phantom: std::marker::PhantomData,
-        }
+    fn mul(mut self, rhs: f32) -> Self::Output {
+        self *= rhs;
+        self
     }
 }
 
-impl<Index: LandmarkIndex> Mul<f32> for Landmarks<Index>
-where
-    Index: Copy + Into<usize>,
-{
-    type Output = Self;
+/// Serde only implements arrays up to 32 elements, the 33 pose landmarks are written as a
+/// sequence like the `Vec` they used to be.
+mod landmark_array {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-    fn mul(self, rhs: f32) -> Self::Output {
-        // let data: [Landmark; N] = std::array::from_fn(|i| &self.data[i] * rhs);
-        Self {
-            data: self.data.into_iter().map(|v| v * rhs).collect(),
-            _phantom: std::marker::PhantomData,
-        }
+    use super::Landmark;
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        data: &[Landmark; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        data.as_slice().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[Landmark; N], D::Error> {
+        let data = Vec::<Landmark>::deserialize(deserializer)?;
+        let len = data.len();
+        data.try_into()
+            .map_err(|_| D::Error::invalid_length(len, &format!("{} landmarks", N).as_str()))
     }
 }
@@ -1,2 +1,8 @@
+pub mod coordinate_frame;
+pub mod deadband;
+pub mod filter;
 pub mod kalman_filter;
 pub mod landmarks;
+pub mod one_euro_filter;
+pub mod outlier_gate;
+pub mod two_bone_ik;
@@ -0,0 +1,144 @@
+use std::f32::consts::PI;
+use std::ops::{Add, Mul, Sub};
+
+use serde::{Deserialize, Serialize};
+
+use crate::math::filter::{Elements, SmoothingFilter};
+
+/// Speed-adaptive low-pass filter, see <https://gery.casiez.net/1euro/>.
+///
+/// The cutoff frequency grows with the speed of change, so jitter is removed while still and
+/// fast movements follow with little lag. Every element adapts to its own speed, so a waving
+/// hand does not open up the cutoff of a still body.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OneEuroFilter<T> {
+    pub position: T, // Filtered position
+    pub velocity: T, // Filtered rate of change, per second
+
+    /// Cutoff frequency in Hz when still.
+    pub min_cutoff: f32,
+    /// How much the cutoff frequency grows with speed.
+    pub beta: f32,
+    /// Cutoff frequency in Hz of the velocity estimate.
+    pub d_cutoff: f32,
+
+    // Smoothing factor of each element in the last update, kept so updating doesn't allocate
+    #[serde(skip)]
+    alphas: Vec<f32>,
+}
+
+impl<T> OneEuroFilter<T>
+where
+    for<'a> &'a T: Sub<&'a T, Output = T>,
+    T: Clone,
+{
+    pub fn with_params(position: T, min_cutoff: f32, beta: f32, d_cutoff: f32) -> Self {
+        let p = position.clone();
+        Self {
+            position,
+            velocity: (&p - &p),
+            min_cutoff,
+            beta,
+            d_cutoff,
+            alphas: Vec::new(),
+        }
+    }
+}
+
+impl<T> SmoothingFilter<T> for OneEuroFilter<T>
+where
+    for<'a> &'a T: Add<&'a T, Output = T> + Sub<&'a T, Output = T> + Mul<f32, Output = T>,
+    T: Elements,
+{
+    fn update(&mut self, measured_pos: &T, dt: f32) -> &T {
+        if dt <= 0.0 {
+            return &self.position;
+        }
+
+        // Low-pass the rate of change first, the raw one is mostly jitter
+        let raw_velocity = &(measured_pos - &self.position) * (1.0 / dt);
+        let alpha_velocity = smoothing_factor(self.d_cutoff, dt);
+        self.velocity = &self.velocity + &(&(&raw_velocity - &self.velocity) * alpha_velocity);
+
+        self.alphas.resize(measured_pos.element_count(), 0.0);
+        for (i, alpha) in self.alphas.iter_mut().enumerate() {
+            let cutoff = self.min_cutoff + self.beta * self.velocity.element_magnitude(i);
+            *alpha = smoothing_factor(cutoff, dt);
+        }
+        self.position =
+            &self.position + &(measured_pos - &self.position).scale_elements(&self.alphas);
+
+        &self.position
+    }
+
+    fn get(&self) -> &T {
+        &self.position
+    }
+
+    fn velocity(&self) -> Option<&T> {
+        Some(&self.velocity)
+    }
+
+    fn reset(&mut self, position: T) {
+        self.velocity = &position - &position;
+        self.position = position;
+    }
+}
+
+impl<T> OneEuroFilter<T> {
+    pub fn set_min_cutoff(&mut self, min_cutoff: f32) -> &mut Self {
+        self.min_cutoff = min_cutoff;
+        self
+    }
+
+    pub fn set_beta(&mut self, beta: f32) -> &mut Self {
+        self.beta = beta;
+        self
+    }
+}
+
+/// Weight of a new sample in an exponential moving average with the given cutoff frequency.
+fn smoothing_factor(cutoff: f32, dt: f32) -> f32 {
+    let r = 2.0 * PI * cutoff * dt;
+    r / (r + 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec3;
+
+    use super::*;
+    use crate::api::hands_api::HandLandmarks;
+    use crate::math::landmarks::Landmark;
+
+    fn landmarks(first: Vec3, rest: Vec3) -> HandLandmarks {
+        HandLandmarks::from_fn(|i| Landmark {
+            position: if i == 0 { first } else { rest },
+            visibility: 1.0,
+        })
+    }
+
+    #[test]
+    fn each_element_adapts_to_its_own_speed() {
+        let start = landmarks(Vec3::ZERO, Vec3::ZERO);
+        let mut filter = OneEuroFilter::with_params(start, 1.0, 10.0, 1.0);
+
+        // The first landmark sweeps away, the others jitter in place.
+        let dt = 1.0 / 30.0;
+        for frame in 1..=10 {
+            let jitter = if frame % 2 == 0 { 0.001 } else { -0.001 };
+            let measured = landmarks(Vec3::X * frame as f32 * 0.1, Vec3::splat(jitter));
+            filter.update(&measured, dt);
+        }
+
+        let position = filter.get();
+        let swept = position.data[0].position.x;
+        // A cutoff opened up by the sweep would let the jitter through too.
+        assert!(swept > 0.5, "sweep lags behind at {swept}");
+        assert!(
+            position.data[1].position.length() < 0.0005,
+            "jitter passes through at {}",
+            position.data[1].position
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::ops::{Add, Mul, Sub};
+
+use serde::{Deserialize, Serialize};
+
+use crate::math::filter::{Magnitude, SmoothingFilter};
+
+/// After this many rejections in a row the next measurement is accepted anyway, as the tracked
+/// value has most likely really moved there.
+const MAX_CONSECUTIVE_REJECTIONS: u32 = 10;
+
+/// Rejects measurements that land further than `threshold` from a constant velocity prediction,
+/// passing on the prediction instead.
+///
+/// Catches single frame glitches, like swapped limbs or a landmark jumping half a metre, before
+/// they reach the smoothing stages.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OutlierGate<T> {
+    pub position: T, // Last accepted measurement, or the prediction that replaced it
+    pub velocity: T, // Rate of change between the last two accepted measurements
+
+    pub threshold: f32,
+    /// Measurements rejected since the gate was created.
+    pub rejected: u64,
+    consecutive: u32,
+}
+
+impl<T> OutlierGate<T>
+where
+    for<'a> &'a T: Sub<&'a T, Output = T>,
+    T: Clone,
+{
+    pub fn new(position: T, threshold: f32) -> Self {
+        let p = position.clone();
+        Self {
+            position,
+            velocity: (&p - &p),
+            threshold,
+            rejected: 0,
+            consecutive: 0,
+        }
+    }
+}
+
+impl<T> OutlierGate<T> {
+    pub fn set_threshold(&mut self, threshold: f32) -> &mut Self {
+        self.threshold = threshold;
+        self
+    }
+}
+
+impl<T> SmoothingFilter<T> for OutlierGate<T>
+where
+    for<'a> &'a T: Add<&'a T, Output = T> + Sub<&'a T, Output = T> + Mul<f32, Output = T>,
+    T: Clone + Magnitude,
+{
+    fn update(&mut self, measured_pos: &T, dt: f32) -> &T {
+        let predicted_pos = &self.position + &(&self.velocity * dt);
+        let innovation = measured_pos - &predicted_pos;
+
+        if innovation.magnitude() > self.threshold && self.consecutive < MAX_CONSECUTIVE_REJECTIONS
+        {
+            self.rejected += 1;
+            self.consecutive += 1;
+            self.position = predicted_pos;
+            return &self.position;
+        }
+
+        // The velocity is only trusted between two measurements that both passed
+        self.velocity = if self.consecutive == 0 && dt > 0.0 {
+            &(measured_pos - &self.position) * (1.0 / dt)
+        } else {
+            &self.velocity - &self.velocity
+        };
+        self.position = measured_pos.clone();
+        self.consecutive = 0;
+
+        &self.position
+    }
+
+    fn get(&self) -> &T {
+        &self.position
+    }
+
+    fn reset(&mut self, position: T) {
+        self.velocity = &position - &position;
+        self.position = position;
+        self.consecutive = 0;
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::math::Vec3;
+
+/// Solves a chain of two bones of `upper_length` and `lower_length` hanging from `root` so its
+/// end reaches `target`, returning the positions of the middle and end joints.
+///
+/// The chain bends towards `pole`, e.g. the tracked elbow or knee. Targets out of reach are
+/// reached for with the chain stretched straight, and `None` is returned when the chain can't be
+/// solved at all (a target on the root, lengths that aren't positive or anything NaN).
+pub fn solve_two_bone_ik(
+    root: Vec3,
+    target: Vec3,
+    pole: Vec3,
+    upper_length: f32,
+    lower_length: f32,
+) -> Option<(Vec3, Vec3)> {
+    let to_target = target - root;
+    let distance = to_target.length();
+    // Written so NaN fails every check
+    if !(distance > f32::EPSILON && upper_length > 0.0 && lower_length > 0.0) {
+        return None;
+    }
+    let direction = to_target / distance;
+    let distance = distance.clamp(
+        (upper_length - lower_length).abs(),
+        upper_length + lower_length,
+    );
+
+    // Law of cosines for the angle between the upper bone and the line to the target
+    let cos_root = ((upper_length * upper_length + distance * distance
+        - lower_length * lower_length)
+        / (2.0 * upper_length * distance))
+        .clamp(-1.0, 1.0);
+    let sin_root = (1.0 - cos_root * cos_root).sqrt();
+
+    let bend = (pole - root)
+        .reject_from_normalized(direction)
+        .try_normalize()
+        .unwrap_or_else(|| direction.any_orthonormal_vector());
+
+    let mid = root + (direction * cos_root + bend * sin_root) * upper_length;
+    let end = root + direction * distance;
+    Some((mid, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reachable_targets_are_reached() {
+        let root = Vec3::new(0.1, 1.4, 0.0);
+        let target = root + Vec3::new(0.3, -0.3, 0.2);
+        let pole = root + Vec3::new(0.2, 0.0, -0.3);
+        let (mid, end) = solve_two_bone_ik(root, target, pole, 0.3, 0.25).unwrap();
+
+        assert!(end.abs_diff_eq(target, 1e-5));
+        assert!((root.distance(mid) - 0.3).abs() < 1e-5);
+        assert!((mid.distance(end) - 0.25).abs() < 1e-5);
+    }
+
+    #[test]
+    fn chains_bend_towards_the_pole() {
+        let target = Vec3::new(0.4, 0.0, 0.0);
+        for pole in [Vec3::Y, -Vec3::Y, Vec3::Z] {
+            let (mid, _) = solve_two_bone_ik(Vec3::ZERO, target, pole, 0.3, 0.3).unwrap();
+            assert!(
+                mid.normalize().dot(pole) > 0.5,
+                "{mid} does not bend to {pole}"
+            );
+            assert!(mid.dot(pole.cross(Vec3::X)).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn targets_out_of_reach_stretch_the_chain() {
+        let (mid, end) =
+            solve_two_bone_ik(Vec3::ZERO, Vec3::new(0.0, -2.0, 0.0), Vec3::Z, 0.4, 0.5).unwrap();
+        assert!(mid.abs_diff_eq(Vec3::new(0.0, -0.4, 0.0), 1e-5));
+        assert!(end.abs_diff_eq(Vec3::new(0.0, -0.9, 0.0), 1e-5));
+
+        // Too close for unequal bones, folded as far as they go
+        let (_, end) =
+            solve_two_bone_ik(Vec3::ZERO, Vec3::new(0.0, -0.01, 0.0), Vec3::Z, 0.4, 0.3).unwrap();
+        assert!(end.abs_diff_eq(Vec3::new(0.0, -0.1, 0.0), 1e-5));
+    }
+
+    #[test]
+    fn unsolvable_chains_are_none() {
+        assert!(solve_two_bone_ik(Vec3::ZERO, Vec3::ZERO, Vec3::Z, 0.3, 0.3).is_none());
+        assert!(solve_two_bone_ik(Vec3::ZERO, Vec3::X, Vec3::Z, 0.0, 0.3).is_none());
+        assert!(solve_two_bone_ik(Vec3::ZERO, Vec3::NAN, Vec3::Z, 0.3, 0.3).is_none());
+    }
+
+    #[test]
+    fn poles_in_line_with_the_target_still_bend() {
+        let target = Vec3::new(0.0, 0.0, 0.5);
+        let (mid, end) = solve_two_bone_ik(Vec3::ZERO, target, target * 2.0, 0.3, 0.3).unwrap();
+        assert!(end.abs_diff_eq(target, 1e-5));
+        assert!((mid.length() - 0.3).abs() < 1e-5);
+        assert!(mid.reject_from(target).length() > 0.1);
+    }
+}
@@ -1,16 +1,89 @@
+use std::path::{Path, PathBuf};
+
+use bevy::asset::io::file::FileAssetReader;
 use bevy::prelude::*;
 
+use crate::character_control::bone_map::BoneMap;
 use crate::shader_plugin::MaterialOverride;
+use crate::vrm::{Vrm, VrmPlugin};
+
+/// The default avatar model, relative to the `assets` directory.
+pub const MODEL_PATH: &str = "models/model1.glb";
+
+/// Loads the avatar, a `.glb` with a `.bones.ron` bone map next to it or a `.vrm`.
+pub struct ModelPlugin {
+    /// Relative to the `assets` directory.
+    pub path: String,
+}
+
+impl Default for ModelPlugin {
+    fn default() -> Self {
+        Self {
+            path: MODEL_PATH.to_string(),
+        }
+    }
+}
+
+/// The avatar model being shown, relative to the `assets` directory.
+#[derive(Resource, Debug, Clone)]
+pub struct ModelPath(pub String);
 
-pub struct ModelPlugin;
+impl ModelPath {
+    pub fn is_vrm(&self) -> bool {
+        Path::new(&self.0)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("vrm"))
+    }
+
+    /// The model file on disk.
+    pub fn file(&self) -> PathBuf {
+        asset_file(&self.0)
+    }
+}
+
+/// `path` inside the `assets` directory, resolved the way the asset server resolves it rather than
+/// against the working directory, so files read next to the model are found wherever mola is run
+/// from.
+pub fn asset_file(path: impl AsRef<Path>) -> PathBuf {
+    FileAssetReader::new("assets").root_path().join(path)
+}
 
 impl Plugin for ModelPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, add_character);
+        let model_path = ModelPath(self.path.clone());
+
+        // VRM models carry their own humanoid bone table, other models need a bone map file.
+        let bone_map = if model_path.is_vrm() {
+            match Vrm::load(&model_path.file()) {
+                Ok(vrm) => {
+                    let bone_map = vrm.bone_map();
+                    app.insert_resource(vrm);
+                    bone_map
+                }
+                Err(err) => {
+                    error!("{}, bones will be looked up by their humanoid names", err);
+                    BoneMap::default()
+                }
+            }
+        } else {
+            BoneMap::load(&model_path.0).unwrap_or_else(|err| {
+                warn!("{}, bones will be looked up by their humanoid names", err);
+                BoneMap::default()
+            })
+        };
+
+        app.insert_resource(model_path)
+            .insert_resource(bone_map)
+            .add_plugins(VrmPlugin)
+            .add_systems(Startup, add_character);
     }
 }
 
-fn add_character(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let model = asset_server.load(GltfAssetLabel::Scene(0).from_asset("models/model1.glb#Scene0"));
+fn add_character(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    model_path: Res<ModelPath>,
+) {
+    let model = asset_server.load(GltfAssetLabel::Scene(0).from_asset(model_path.0.clone()));
     commands.spawn((SceneRoot(model), MaterialOverride));
 }
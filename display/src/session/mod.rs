@@ -0,0 +1,26 @@
+use std::path::PathBuf;
+
+use bevy::asset::io::file::FileAssetReader;
+use serde::{Deserialize, Serialize};
+
+use crate::api::stream_api::StreamMessage;
+
+pub mod playback;
+pub mod recorder;
+
+/// Directory recordings are written to, next to the `assets` directory rather than in the working
+/// directory, so they are found again wherever mola is run from.
+pub fn recordings_dir() -> PathBuf {
+    FileAssetReader::get_base_path().join("recordings")
+}
+
+/// One line of a recorded session file.
+///
+/// Frames are stored as the raw payloads that were accepted by the API, so a recording can be
+/// fed back through the same conversion and filtering as live data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    /// Seconds since the recording was started, measured when the frame arrived.
+    pub time: f64,
+    pub frame: StreamMessage,
+}
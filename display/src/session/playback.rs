@@ -0,0 +1,254 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::prelude::*;
+
+use crate::api::face_api::CurrentFace;
+use crate::api::hands_api::CurrentHands;
+use crate::api::ingest::{ActiveSource, FrameClock};
+use crate::api::pose_api::CurrentPose;
+use crate::api::stream_api::{StreamKind, StreamMessage};
+use crate::character_control::pose::PoseData;
+use crate::character_control::rotate_body::rotate_body;
+use crate::character_control::rotate_hands::rotate_hands;
+use crate::session::SessionRecord;
+use crate::ui::state::GuiState;
+
+/// Replays a recorded session into [`CurrentPose`], [`CurrentHands`] and [`CurrentFace`] at its
+/// original timing, in place of live input.
+pub struct SessionPlaybackPlugin;
+
+impl Plugin for SessionPlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SessionPlayer>().add_systems(
+            Update,
+            advance_playback.before(rotate_body).before(rotate_hands),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct SessionPlayer {
+    pub path: Option<PathBuf>,
+    records: Vec<SessionRecord>,
+    /// Playback position in seconds of recorded time.
+    position: f64,
+    /// Index of the first record after `position`.
+    next: usize,
+    pub playing: bool,
+    pub looping: bool,
+    pub speed: f32,
+    /// Set by a seek, so the filters start over instead of smoothing across the jump.
+    needs_reset: bool,
+    pose_clock: FrameClock,
+    hands_clock: FrameClock,
+    face_clock: FrameClock,
+}
+
+impl Default for SessionPlayer {
+    fn default() -> Self {
+        Self {
+            path: None,
+            records: Vec::new(),
+            position: 0.0,
+            next: 0,
+            playing: false,
+            looping: true,
+            speed: 1.0,
+            needs_reset: false,
+            pose_clock: FrameClock::default(),
+            hands_clock: FrameClock::default(),
+            face_clock: FrameClock::default(),
+        }
+    }
+}
+
+impl SessionPlayer {
+    /// Loads a session file and rewinds to its start, returning the number of frames.
+    pub fn load(&mut self, path: &Path) -> Result<usize, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+
+        let mut records = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: SessionRecord = serde_json::from_str(line)
+                .map_err(|err| format!("{}:{}: {}", path.display(), i + 1, err))?;
+            records.push(record);
+        }
+        // Frames from different streams may have been written slightly out of order.
+        records.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        self.records = records;
+        self.path = Some(path.to_path_buf());
+        self.seek(0.0);
+        Ok(self.records.len())
+    }
+
+    pub fn unload(&mut self) {
+        *self = Self {
+            looping: self.looping,
+            speed: self.speed,
+            ..Self::default()
+        };
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.path.is_some()
+    }
+
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.records.last().map(|r| r.time).unwrap_or(0.0)
+    }
+
+    pub fn seek(&mut self, position: f64) {
+        self.position = position.clamp(0.0, self.duration());
+        self.next = self.records.partition_point(|r| r.time <= self.position);
+        self.needs_reset = true;
+    }
+
+    /// The latest frame of each stream at or before the current position.
+    fn frames_at_position(&self) -> Vec<SessionRecord> {
+        let mut frames: Vec<SessionRecord> = Vec::new();
+        for record in self.records[..self.next].iter().rev() {
+            if !frames.iter().any(|r| r.frame.kind() == record.frame.kind()) {
+                frames.push(record.clone());
+            }
+            if frames.len() == 3 {
+                break;
+            }
+        }
+        frames.reverse();
+        frames
+    }
+
+    /// Advances the position by `delta` seconds of real time and returns the frames passed.
+    fn advance(&mut self, delta: f64) -> Vec<SessionRecord> {
+        self.position += delta * self.speed as f64;
+
+        let end =
+            self.next + self.records[self.next..].partition_point(|r| r.time <= self.position);
+        let frames = self.records[self.next..end].to_vec();
+        self.next = end;
+
+        if self.next >= self.records.len() {
+            if self.looping {
+                self.seek(0.0);
+            } else {
+                self.position = self.duration();
+                self.playing = false;
+            }
+        }
+
+        frames
+    }
+
+    fn clock(&mut self, kind: StreamKind) -> &mut FrameClock {
+        match kind {
+            StreamKind::Pose => &mut self.pose_clock,
+            StreamKind::Hands => &mut self.hands_clock,
+            StreamKind::Face => &mut self.face_clock,
+        }
+    }
+}
+
+#[hot]
+fn advance_playback(
+    time: Res<Time>,
+    gui_state: Res<GuiState>,
+    mut player: ResMut<SessionPlayer>,
+    mut active_source: ResMut<ActiveSource>,
+    mut current_pose: ResMut<CurrentPose>,
+    mut current_hands: ResMut<CurrentHands>,
+    mut current_face: ResMut<CurrentFace>,
+) {
+    if !player.is_loaded() {
+        if *active_source == ActiveSource::Playback {
+            *active_source = ActiveSource::Live;
+        }
+        return;
+    }
+    if *active_source != ActiveSource::Playback {
+        *active_source = ActiveSource::Playback;
+    }
+
+    let frames = if player.needs_reset {
+        player.needs_reset = false;
+        player.pose_clock = FrameClock::default();
+        player.hands_clock = FrameClock::default();
+        player.face_clock = FrameClock::default();
+        *current_pose = CurrentPose::default();
+        *current_hands = CurrentHands::default();
+        *current_face = CurrentFace::default();
+        // Show where we landed, even while paused.
+        player.frames_at_position()
+    } else if player.playing {
+        player.advance(time.delta_secs_f64())
+    } else {
+        return;
+    };
+
+    for record in frames {
+        // Recordings without capture timestamps are timed by their arrival.
+        let capture_ms = frame_timestamp(&record.frame).unwrap_or(record.time * 1000.0);
+        let dt = match player
+            .clock(record.frame.kind())
+            .tick(Some(capture_ms), Instant::now())
+        {
+            Ok(dt) => dt,
+            Err(err) => {
+                warn!("Skipping recorded frame: {}", err);
+                continue;
+            }
+        };
+
+        match record.frame {
+            StreamMessage::Pose(payload) => {
+                if !gui_state.update_pose_data {
+                    continue;
+                }
+                match PoseData::from_json(&payload, &gui_state.mediapipe_frame()) {
+                    Ok(pose_data) => {
+                        current_pose.apply(pose_data, dt, &gui_state.pose_filter_config())
+                    }
+                    Err(err) => warn!("Skipping recorded pose: {}", err),
+                }
+            }
+            StreamMessage::Hands(payload) => {
+                if !gui_state.update_hands_data {
+                    continue;
+                }
+                let config = gui_state.hands_filter_config();
+                match CurrentHands::from_json(
+                    payload.hand_landmarker_result,
+                    &gui_state.mediapipe_frame(),
+                    &config,
+                ) {
+                    Ok(hands) => current_hands.apply(hands, dt, &config),
+                    Err(err) => warn!("Skipping recorded hands: {}", err),
+                }
+            }
+            StreamMessage::Face(payload) => current_face.apply(
+                &payload.face_landmarker_result,
+                dt,
+                &gui_state.face_filter_config(),
+            ),
+        }
+    }
+}
+
+fn frame_timestamp(frame: &StreamMessage) -> Option<f64> {
+    match frame {
+        StreamMessage::Pose(payload) => payload.timestamp,
+        StreamMessage::Hands(payload) => payload.timestamp,
+        StreamMessage::Face(payload) => payload.timestamp,
+    }
+}
@@ -0,0 +1,134 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy_defer::{AsyncAccess, AsyncWorld};
+
+use crate::api::stream_api::StreamMessage;
+use crate::session::{SessionRecord, recordings_dir};
+use crate::ui::state::GuiState;
+
+/// Writes every accepted pose, hands and face payload to a newline-delimited JSON file.
+///
+/// Recording is toggled with [`GuiState::record_session`], from the settings window or the
+/// `/start_recording` and `/stop_recording` routes.
+pub struct SessionRecorderPlugin;
+
+impl Plugin for SessionRecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SessionRecorder>()
+            .add_systems(Update, sync_session_recording);
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SessionRecorder {
+    writer: Option<BufWriter<File>>,
+    started: Option<Instant>,
+    path: Option<PathBuf>,
+    frames: usize,
+    /// The last value of [`GuiState::record_session`] acted upon.
+    requested: bool,
+}
+
+impl SessionRecorder {
+    pub fn is_recording(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    fn start(&mut self) -> std::io::Result<PathBuf> {
+        let dir = recordings_dir();
+        fs::create_dir_all(&dir)?;
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let path = dir.join(format!("session-{stamp}.ndjson"));
+
+        self.writer = Some(BufWriter::new(File::create(&path)?));
+        self.started = Some(Instant::now());
+        self.path = Some(path.clone());
+        self.frames = 0;
+        Ok(path)
+    }
+
+    fn stop(&mut self) {
+        let Some(mut writer) = self.writer.take() else {
+            return;
+        };
+        if let Err(err) = writer.flush() {
+            error!("Failed to flush session recording: {}", err);
+        }
+        if let Some(path) = self.path.take() {
+            info!("Recorded {} frames to {}", self.frames, path.display());
+        }
+        self.started = None;
+    }
+
+    fn write(&mut self, frame: StreamMessage) -> std::io::Result<()> {
+        let (Some(writer), Some(started)) = (self.writer.as_mut(), self.started) else {
+            return Ok(());
+        };
+        let record = SessionRecord {
+            time: started.elapsed().as_secs_f64(),
+            frame,
+        };
+        serde_json::to_writer(&mut *writer, &record)?;
+        writer.write_all(b"\n")?;
+        self.frames += 1;
+        Ok(())
+    }
+}
+
+fn sync_session_recording(mut gui_state: ResMut<GuiState>, mut recorder: ResMut<SessionRecorder>) {
+    if gui_state.record_session == recorder.requested {
+        // Recording stopped on its own after a write error, so reflect that in the toggle.
+        if recorder.requested && !recorder.is_recording() {
+            gui_state.record_session = false;
+            recorder.requested = false;
+        }
+        return;
+    }
+
+    recorder.requested = gui_state.record_session;
+    if gui_state.record_session {
+        match recorder.start() {
+            Ok(path) => info!("Recording session to {}", path.display()),
+            Err(err) => error!("Failed to start session recording: {}", err),
+        }
+    } else {
+        recorder.stop();
+    }
+}
+
+/// Clones `payload` if a recording is running, so it can be written once it has been accepted.
+pub fn clone_if_recording<T: Clone>(payload: &T) -> Option<T> {
+    AsyncWorld
+        .resource::<SessionRecorder>()
+        .get(|recorder| recorder.is_recording())
+        .unwrap_or(false)
+        .then(|| payload.clone())
+}
+
+/// Appends an accepted frame to the running recording, if any.
+pub fn record_frame(frame: StreamMessage) {
+    let result =
+        AsyncWorld
+            .resource::<SessionRecorder>()
+            .get_mut(|recorder: &mut SessionRecorder| {
+                let result = recorder.write(frame);
+                if result.is_err() {
+                    recorder.writer = None;
+                    recorder.started = None;
+                }
+                result
+            });
+
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => error!("Failed to write session recording, stopping: {}", err),
+        Err(err) => error!("Error accessing SessionRecorder: {}", err),
+    }
+}
@@ -0,0 +1,104 @@
+//! Converts a [`Take`] into a BVH file sampled at a fixed frame rate.
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::bvh::writer::write_bvh;
+use crate::bvh::{Bvh, BvhChannel, BvhJoint};
+use crate::take::Take;
+
+const POSITION_CHANNELS: [BvhChannel; 3] = [
+    BvhChannel::Xposition,
+    BvhChannel::Yposition,
+    BvhChannel::Zposition,
+];
+/// Rotations are written as `Rz * Rx * Ry`, the most common order among DCC tools.
+const ROTATION_CHANNELS: [BvhChannel; 3] = [
+    BvhChannel::Zrotation,
+    BvhChannel::Xrotation,
+    BvhChannel::Yrotation,
+];
+
+pub fn export_bvh(take: &Take, frame_rate: f32, output: &Path) -> io::Result<()> {
+    let bvh = take_to_bvh(take, frame_rate);
+    write_bvh(&bvh, BufWriter::new(File::create(output)?))
+}
+
+/// Builds a BVH whose zero rotation is the avatar's rest pose.
+///
+/// BVH joints have no rest orientation, so the rest rotations are moved into the offsets and
+/// every frame is expressed relative to the rest pose, in the frame of the skeleton root.
+pub fn take_to_bvh(take: &Take, frame_rate: f32) -> Bvh {
+    // Rest rotation of every bone relative to the skeleton root's parent.
+    let mut rest_global = Vec::with_capacity(take.bones.len());
+    for bone in &take.bones {
+        let parent = bone
+            .parent
+            .map(|p| rest_global[p])
+            .unwrap_or(Quat::IDENTITY);
+        rest_global.push(parent * bone.rest.rotation);
+    }
+    let parent_rest = |i: usize| {
+        take.bones[i]
+            .parent
+            .map(|p| rest_global[p])
+            .unwrap_or(Quat::IDENTITY)
+    };
+
+    let mut joints: Vec<BvhJoint> = take
+        .bones
+        .iter()
+        .enumerate()
+        .map(|(i, bone)| {
+            let (offset, channels) = match bone.parent {
+                // The root position is written in full by its position channels.
+                None => (Vec3::ZERO, [POSITION_CHANNELS, ROTATION_CHANNELS].concat()),
+                Some(_) => (
+                    parent_rest(i) * bone.rest.translation,
+                    ROTATION_CHANNELS.to_vec(),
+                ),
+            };
+            BvhJoint {
+                name: bone.name.clone(),
+                parent: bone.parent,
+                offset,
+                channels,
+                end_site: None,
+            }
+        })
+        .collect();
+
+    // Give leaf joints some length, by continuing in the direction of their own offset.
+    for i in 0..joints.len() {
+        if !joints.iter().any(|j| j.parent == Some(i)) {
+            joints[i].end_site = Some(joints[i].offset);
+        }
+    }
+
+    let frame_rate = frame_rate.max(1.0);
+    let frame_count = (take.duration() * frame_rate).floor() as usize + 1;
+    let frames = (0..frame_count)
+        .map(|f| {
+            let transforms = take.sample(f as f32 / frame_rate);
+            let mut values = Vec::new();
+            for (i, transform) in transforms.iter().enumerate() {
+                if take.bones[i].parent.is_none() {
+                    values.extend(transform.translation.to_array());
+                }
+                let rotation = parent_rest(i) * transform.rotation * rest_global[i].inverse();
+                let (z, x, y) = rotation.to_euler(EulerRot::ZXY);
+                values.extend([z.to_degrees(), x.to_degrees(), y.to_degrees()]);
+            }
+            values
+        })
+        .collect();
+
+    Bvh {
+        joints,
+        frame_time: 1.0 / frame_rate,
+        frames,
+    }
+}
@@ -0,0 +1,285 @@
+//! Bakes a [`Take`] into a copy of the avatar's `.glb` as a glTF animation clip.
+
+use std::fs;
+use std::path::Path;
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use serde_json::{Value, json};
+
+use crate::take::Take;
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const CHUNK_JSON: u32 = 0x4E4F534A;
+const CHUNK_BIN: u32 = 0x004E4942;
+
+// glTF accessor component type and buffer view alignment.
+const FLOAT: u32 = 5126;
+const ALIGNMENT: usize = 4;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GltfExportError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid glTF JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid GLB: {0}")]
+    InvalidGlb(&'static str),
+    #[error("Take has no frames")]
+    EmptyTake,
+}
+
+/// Writes `model` with `take` appended as an animation named `name` to `output`.
+///
+/// Every bone of the take gets a rotation channel, root bones get a translation channel too.
+/// Bones are matched to glTF nodes by name, preferring skin joints when names are ambiguous.
+pub fn export_take(
+    take: &Take,
+    name: &str,
+    model: &Path,
+    output: &Path,
+) -> Result<(), GltfExportError> {
+    if take.frames.is_empty() {
+        return Err(GltfExportError::EmptyTake);
+    }
+
+    let glb = fs::read(model)?;
+    let (mut gltf, mut bin) = read_glb(&glb)?;
+
+    let buffer_length = gltf["buffers"][0]["byteLength"]
+        .as_u64()
+        .ok_or(GltfExportError::InvalidGlb("missing buffer 0"))? as usize;
+    bin.truncate(buffer_length);
+
+    let nodes = node_indices(&gltf);
+    let mut writer = AccessorWriter::new(&gltf, &mut bin);
+
+    let times: Vec<f32> = take.frames.iter().map(|f| f.time).collect();
+    let input = writer.push_times(&times);
+
+    let mut samplers = Vec::new();
+    let mut channels = Vec::new();
+    for (i, bone) in take.bones.iter().enumerate() {
+        let Some(&node) = nodes.get(bone.name.as_str()) else {
+            warn!(
+                "Bone {} not found in {}, skipping",
+                bone.name,
+                model.display()
+            );
+            continue;
+        };
+
+        let mut previous = Quat::IDENTITY;
+        let rotations: Vec<f32> = take
+            .frames
+            .iter()
+            .flat_map(|frame| {
+                // Keep consecutive keys in the same hemisphere, so linear interpolation does not
+                // take the long way around.
+                let mut rotation = frame.transforms[i].rotation;
+                if rotation.dot(previous) < 0.0 {
+                    rotation = -rotation;
+                }
+                previous = rotation;
+                rotation.to_array()
+            })
+            .collect();
+        let output = writer.push_floats(&rotations, "VEC4");
+        channels.push(json!({
+            "sampler": samplers.len(),
+            "target": { "node": node, "path": "rotation" },
+        }));
+        samplers.push(json!({
+            "input": input,
+            "output": output,
+            "interpolation": "LINEAR",
+        }));
+
+        if bone.parent.is_none() {
+            let translations: Vec<f32> = take
+                .frames
+                .iter()
+                .flat_map(|frame| frame.transforms[i].translation.to_array())
+                .collect();
+            let output = writer.push_floats(&translations, "VEC3");
+            channels.push(json!({
+                "sampler": samplers.len(),
+                "target": { "node": node, "path": "translation" },
+            }));
+            samplers.push(json!({
+                "input": input,
+                "output": output,
+                "interpolation": "LINEAR",
+            }));
+        }
+    }
+
+    writer.finish(&mut gltf);
+
+    let animation = json!({ "name": name, "samplers": samplers, "channels": channels });
+    match gltf.get_mut("animations").and_then(Value::as_array_mut) {
+        Some(animations) => animations.push(animation),
+        None => gltf["animations"] = json!([animation]),
+    }
+    gltf["buffers"][0]["byteLength"] = json!(bin.len());
+
+    fs::write(output, write_glb(&gltf, &bin)?)?;
+    Ok(())
+}
+
+/// Splits a GLB file into its JSON and binary chunks.
+fn read_glb(glb: &[u8]) -> Result<(Value, Vec<u8>), GltfExportError> {
+    if glb.len() < 12 || &glb[0..4] != GLB_MAGIC {
+        return Err(GltfExportError::InvalidGlb("not a GLB file"));
+    }
+
+    let mut json = None;
+    let mut bin = Vec::new();
+    let mut offset = 12;
+    while offset + 8 <= glb.len() {
+        let length = read_u32(glb, offset) as usize;
+        let kind = read_u32(glb, offset + 4);
+        let data = glb
+            .get(offset + 8..offset + 8 + length)
+            .ok_or(GltfExportError::InvalidGlb("chunk out of bounds"))?;
+        match kind {
+            CHUNK_JSON => json = Some(serde_json::from_slice(data)?),
+            CHUNK_BIN => bin = data.to_vec(),
+            // Unknown chunks must be ignored.
+            _ => {}
+        }
+        offset += 8 + length;
+    }
+
+    let json = json.ok_or(GltfExportError::InvalidGlb("missing JSON chunk"))?;
+    Ok((json, bin))
+}
+
+fn write_glb(gltf: &Value, bin: &[u8]) -> Result<Vec<u8>, GltfExportError> {
+    let mut json = serde_json::to_vec(gltf)?;
+    // The JSON chunk is padded with spaces, the binary one with zeros.
+    json.resize(padded(json.len()), b' ');
+    let bin_length = padded(bin.len());
+
+    let total = 12 + 8 + json.len() + 8 + bin_length;
+    let mut glb = Vec::with_capacity(total);
+    glb.extend_from_slice(GLB_MAGIC);
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(total as u32).to_le_bytes());
+
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(&CHUNK_JSON.to_le_bytes());
+    glb.extend_from_slice(&json);
+
+    glb.extend_from_slice(&(bin_length as u32).to_le_bytes());
+    glb.extend_from_slice(&CHUNK_BIN.to_le_bytes());
+    glb.extend_from_slice(bin);
+    glb.resize(total, 0);
+
+    Ok(glb)
+}
+
+/// Maps node names to indices, skin joints taking precedence over other nodes of the same name.
+fn node_indices(gltf: &Value) -> HashMap<String, usize> {
+    let nodes = gltf["nodes"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let name = |i: usize| nodes.get(i).and_then(|n| n["name"].as_str());
+
+    let mut indices = HashMap::new();
+    for i in 0..nodes.len() {
+        if let Some(name) = name(i) {
+            indices.entry(name.to_string()).or_insert(i);
+        }
+    }
+
+    let skins = gltf["skins"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    for joint in skins
+        .iter()
+        .filter_map(|skin| skin["joints"].as_array())
+        .flatten()
+        .filter_map(Value::as_u64)
+    {
+        if let Some(name) = name(joint as usize) {
+            indices.insert(name.to_string(), joint as usize);
+        }
+    }
+
+    indices
+}
+
+/// Appends float data to the binary chunk and declares a buffer view and accessor for it.
+struct AccessorWriter<'a> {
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+    bin: &'a mut Vec<u8>,
+}
+
+impl<'a> AccessorWriter<'a> {
+    fn new(gltf: &Value, bin: &'a mut Vec<u8>) -> Self {
+        Self {
+            buffer_views: gltf["bufferViews"].as_array().cloned().unwrap_or_default(),
+            accessors: gltf["accessors"].as_array().cloned().unwrap_or_default(),
+            bin,
+        }
+    }
+
+    /// Animation inputs must declare their range.
+    fn push_times(&mut self, times: &[f32]) -> usize {
+        let index = self.push_floats(times, "SCALAR");
+        let min = times.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = times.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        self.accessors[index]["min"] = json!([min]);
+        self.accessors[index]["max"] = json!([max]);
+        index
+    }
+
+    fn push_floats(&mut self, values: &[f32], kind: &str) -> usize {
+        let components = match kind {
+            "VEC3" => 3,
+            "VEC4" => 4,
+            _ => 1,
+        };
+
+        self.bin.resize(padded(self.bin.len()), 0);
+        let offset = self.bin.len();
+        for value in values {
+            self.bin.extend_from_slice(&value.to_le_bytes());
+        }
+
+        self.buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": self.bin.len() - offset,
+        }));
+        self.accessors.push(json!({
+            "bufferView": self.buffer_views.len() - 1,
+            "componentType": FLOAT,
+            "count": values.len() / components,
+            "type": kind,
+        }));
+        self.accessors.len() - 1
+    }
+
+    fn finish(self, gltf: &mut Value) {
+        gltf["bufferViews"] = Value::Array(self.buffer_views);
+        gltf["accessors"] = Value::Array(self.accessors);
+    }
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
+
+fn padded(len: usize) -> usize {
+    len.div_ceil(ALIGNMENT) * ALIGNMENT
+}
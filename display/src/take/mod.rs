@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+
+pub mod bvh;
+pub mod gltf;
+pub mod recorder;
+
+/// Local bone transforms of the avatar, sampled once per rendered frame.
+#[derive(Debug, Clone, Default)]
+pub struct Take {
+    /// The sampled skeleton, parents always coming before their children.
+    pub bones: Vec<TakeBone>,
+    pub frames: Vec<TakeFrame>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TakeBone {
+    /// Node name in the avatar model.
+    pub name: String,
+    pub parent: Option<usize>,
+    /// Local transform of the bone as loaded from the model.
+    pub rest: Transform,
+}
+
+#[derive(Debug, Clone)]
+pub struct TakeFrame {
+    /// Seconds since the take was started.
+    pub time: f32,
+    /// Local transforms, in the order of [`Take::bones`].
+    pub transforms: Vec<Transform>,
+}
+
+impl Take {
+    pub fn duration(&self) -> f32 {
+        self.frames.last().map(|f| f.time).unwrap_or(0.0)
+    }
+
+    /// Interpolates the local transform of every bone at `time`.
+    pub fn sample(&self, time: f32) -> Vec<Transform> {
+        let next = self.frames.partition_point(|f| f.time <= time);
+        let (Some(a), Some(b)) = (
+            self.frames.get(next.saturating_sub(1)),
+            self.frames
+                .get(next.min(self.frames.len().saturating_sub(1))),
+        ) else {
+            return self.bones.iter().map(|b| b.rest).collect();
+        };
+
+        let span = b.time - a.time;
+        let t = if span > 0.0 {
+            ((time - a.time) / span).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        a.transforms
+            .iter()
+            .zip(&b.transforms)
+            .map(|(a, b)| Transform {
+                translation: a.translation.lerp(b.translation, t),
+                rotation: a.rotation.slerp(b.rotation, t),
+                scale: a.scale.lerp(b.scale, t),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take() -> Take {
+        let frame = |time, x: f32, angle: f32| TakeFrame {
+            time,
+            transforms: vec![Transform {
+                translation: Vec3::new(x, 0.0, 0.0),
+                rotation: Quat::from_rotation_y(angle),
+                scale: Vec3::ONE,
+            }],
+        };
+        Take {
+            bones: vec![TakeBone {
+                name: "Hips".to_string(),
+                parent: None,
+                rest: Transform::from_xyz(0.0, 1.0, 0.0),
+            }],
+            frames: vec![frame(1.0, 0.0, 0.0), frame(2.0, 2.0, 1.0)],
+        }
+    }
+
+    #[test]
+    fn samples_between_frames_are_interpolated() {
+        let sample = take().sample(1.25)[0];
+        assert!(
+            sample
+                .translation
+                .abs_diff_eq(Vec3::new(0.5, 0.0, 0.0), 1e-6)
+        );
+        assert!(
+            sample
+                .rotation
+                .abs_diff_eq(Quat::from_rotation_y(0.25), 1e-6)
+        );
+    }
+
+    #[test]
+    fn samples_outside_the_take_hold_the_end_frames() {
+        let take = take();
+        assert_eq!(take.sample(0.0)[0], take.frames[0].transforms[0]);
+        assert_eq!(take.sample(1.0)[0], take.frames[0].transforms[0]);
+        assert_eq!(take.sample(2.0)[0], take.frames[1].transforms[0]);
+        assert_eq!(take.sample(5.0)[0], take.frames[1].transforms[0]);
+        assert_eq!(take.duration(), 2.0);
+    }
+
+    #[test]
+    fn empty_takes_sample_the_rest_pose() {
+        let take = Take {
+            frames: Vec::new(),
+            ..take()
+        };
+        assert_eq!(take.sample(1.0), vec![take.bones[0].rest]);
+        assert_eq!(take.duration(), 0.0);
+    }
+}
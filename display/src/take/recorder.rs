@@ -0,0 +1,191 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::platform::collections::HashSet;
+use bevy::prelude::*;
+use bevy::tasks::IoTaskPool;
+
+use crate::character_control::character_controller::CharacterParts;
+use crate::character_control::humanoid::RestPose;
+use crate::model_plugin::ModelPath;
+use crate::take::bvh::export_bvh;
+use crate::take::gltf::export_take;
+use crate::take::{Take, TakeBone, TakeFrame};
+use crate::ui::state::GuiState;
+
+/// Samples the retargeted bone transforms while [`GuiState::record_take`] is on, and exports
+/// them as `.glb` and/or `.bvh` next to the avatar model when it is turned off.
+pub struct TakeRecorderPlugin;
+
+impl Plugin for TakeRecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TakeRecorder>()
+            // Runs after every Update system has written its local transforms.
+            .add_systems(PostUpdate, sample_take);
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct TakeRecorder {
+    take: Option<Take>,
+    /// Bone entities, in the order of [`Take::bones`].
+    entities: Vec<Entity>,
+    elapsed: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TakeExport {
+    gltf: bool,
+    bvh: bool,
+    bvh_frame_rate: f32,
+}
+
+fn sample_take(
+    gui_state: Res<GuiState>,
+    parts: Res<CharacterParts>,
+    rest_pose: Res<RestPose>,
+    model_path: Res<ModelPath>,
+    time: Res<Time>,
+    mut recorder: ResMut<TakeRecorder>,
+    bone_q: Query<(&Name, &Transform)>,
+    child_of_q: Query<&ChildOf>,
+    children_q: Query<&Children>,
+) {
+    match (gui_state.record_take, recorder.take.is_some()) {
+        (true, false) => {
+            let skeleton = collect_skeleton(&parts, &rest_pose, &bone_q, &child_of_q, &children_q);
+            if skeleton.is_empty() {
+                return;
+            }
+            let (entities, bones): (Vec<_>, Vec<_>) = skeleton.into_iter().unzip();
+            info!("Recording take of {} bones", bones.len());
+            recorder.entities = entities;
+            recorder.take = Some(Take {
+                bones,
+                frames: Vec::new(),
+            });
+            recorder.elapsed = 0.0;
+        }
+        (false, true) => {
+            if let Some(take) = recorder.take.take() {
+                save_take(
+                    take,
+                    &model_path,
+                    TakeExport {
+                        gltf: gui_state.export_gltf,
+                        bvh: gui_state.export_bvh,
+                        bvh_frame_rate: gui_state.bvh_frame_rate,
+                    },
+                );
+            }
+            return;
+        }
+        (false, false) => return,
+        (true, true) => recorder.elapsed += time.delta_secs(),
+    }
+
+    let recorder = &mut *recorder;
+    let transforms = recorder
+        .entities
+        .iter()
+        .map(|&entity| {
+            bone_q
+                .get(entity)
+                .map(|(_, transform)| *transform)
+                .unwrap_or_default()
+        })
+        .collect();
+    if let Some(take) = recorder.take.as_mut() {
+        take.frames.push(TakeFrame {
+            time: recorder.elapsed,
+            transforms,
+        });
+    }
+}
+
+/// Collects the found bones and every node between them and the root, parents first.
+fn collect_skeleton(
+    parts: &CharacterParts,
+    rest_pose: &RestPose,
+    bone_q: &Query<(&Name, &Transform)>,
+    child_of_q: &Query<&ChildOf>,
+    children_q: &Query<&Children>,
+) -> Vec<(Entity, TakeBone)> {
+    let Some(root) = parts.root else {
+        warn!("Cannot record a take before the avatar is loaded");
+        return Vec::new();
+    };
+
+    let mut included = HashSet::new();
+    included.insert(root);
+    for (_, entity) in parts.found_bones() {
+        let mut chain = Vec::new();
+        let mut current = entity;
+        // Bones that are not below the root are left out.
+        while let Ok(child_of) = child_of_q.get(current) {
+            chain.push(current);
+            if included.contains(&child_of.parent()) {
+                included.extend(chain);
+                break;
+            }
+            current = child_of.parent();
+        }
+    }
+
+    let mut skeleton = Vec::new();
+    let mut stack = vec![(root, None)];
+    while let Some((entity, parent)) = stack.pop() {
+        let Ok((name, transform)) = bone_q.get(entity) else {
+            continue;
+        };
+        let index = skeleton.len();
+        skeleton.push((
+            entity,
+            TakeBone {
+                name: name.to_string(),
+                parent,
+                rest: rest_pose.0.get(&entity).copied().unwrap_or(*transform),
+            },
+        ));
+        if let Ok(children) = children_q.get(entity) {
+            for child in children.iter().rev() {
+                if included.contains(&child) {
+                    stack.push((child, Some(index)));
+                }
+            }
+        }
+    }
+    skeleton
+}
+
+/// Writes the take on the IO task pool, so a long take does not stall a frame.
+fn save_take(take: Take, model_path: &ModelPath, export: TakeExport) {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let model = model_path.file();
+    let output = model.with_file_name(format!(
+        "{}-take-{stamp}",
+        model.file_stem().unwrap_or_default().to_string_lossy()
+    ));
+
+    IoTaskPool::get()
+        .spawn(async move {
+            if export.gltf {
+                let output = output.with_extension("glb");
+                let name = format!("Take {stamp}");
+                match export_take(&take, &name, &model, &output) {
+                    Ok(()) => info!("Saved {:.1}s take to {}", take.duration(), output.display()),
+                    Err(err) => error!("Failed to save take: {}", err),
+                }
+            }
+            if export.bvh {
+                let output = output.with_extension("bvh");
+                match export_bvh(&take, export.bvh_frame_rate, &output) {
+                    Ok(()) => info!("Saved {:.1}s take to {}", take.duration(), output.display()),
+                    Err(err) => error!("Failed to save BVH: {}", err),
+                }
+            }
+        })
+        .detach();
+}
@@ -1,5 +1,6 @@
 pub mod slider;
 pub mod state;
 pub mod theme;
+pub mod timeline;
 pub mod toggle_switch;
 pub mod ui_controller;
@@ -2,13 +2,17 @@ use bevy::prelude::*;
 use bevy::{ecs::resource::Resource, reflect::Reflect};
 use typed_builder::TypedBuilder;
 
+use crate::character_control::limb_ik::LimbSolver;
+use crate::math::coordinate_frame::CoordinateFrame;
+use crate::math::filter::{FilterConfig, FilterKind, PredictionConfig};
+
 #[derive(Reflect)]
 pub struct SliderRange(pub f32, pub f32);
 
 #[derive(Reflect)]
 pub struct Separator;
 
-#[derive(Resource, Debug, TypedBuilder, Default, Reflect)]
+#[derive(Resource, Debug, TypedBuilder, Reflect)]
 #[reflect(Resource)]
 pub struct GuiState {
     #[builder(default = false)]
@@ -26,6 +30,91 @@ pub struct GuiState {
     pub update_pose_data: bool,
     #[builder(default = true)]
     pub update_hands_data: bool,
+    /// Mirrors the MediaPipe landmarks, for front-facing cameras.
+    #[builder(default = false)]
+    pub mirror_camera: bool,
+
+    #[reflect(@Separator)]
+    #[reflect(@SliderRange(0.0, 1.0))]
+    #[builder(default = 0.3)]
+    pub pose_gate: f32,
+    #[builder(default = FilterKind::Kalman)]
+    pub pose_filter: FilterKind,
+    #[reflect(@SliderRange(1.0, 500.0))]
+    #[builder(default = 100.0)]
+    pub pose_measurement_noise: f32,
+    #[reflect(@SliderRange(0.01, 10.0))]
+    #[builder(default = 1.0)]
+    pub pose_min_cutoff: f32,
+    #[reflect(@SliderRange(0.0, 20.0))]
+    #[builder(default = 2.0)]
+    pub pose_beta: f32,
+    #[reflect(@SliderRange(0.0, 0.05))]
+    #[builder(default = 0.)]
+    pub pose_deadband: f32,
+    #[reflect(@SliderRange(0.0, 1.0))]
+    #[builder(default = 0.)]
+    pub hands_gate: f32,
+    #[builder(default = FilterKind::Kalman)]
+    pub hands_filter: FilterKind,
+    #[reflect(@SliderRange(1.0, 500.0))]
+    #[builder(default = 100.0)]
+    pub hands_measurement_noise: f32,
+    #[reflect(@SliderRange(0.01, 10.0))]
+    #[builder(default = 1.0)]
+    pub hands_min_cutoff: f32,
+    #[reflect(@SliderRange(0.0, 20.0))]
+    #[builder(default = 2.0)]
+    pub hands_beta: f32,
+    #[reflect(@SliderRange(0.0, 0.05))]
+    #[builder(default = 0.)]
+    pub hands_deadband: f32,
+    #[reflect(@SliderRange(0.0, 1.0))]
+    #[builder(default = 0.)]
+    pub face_gate: f32,
+    #[builder(default = FilterKind::Off)]
+    pub face_filter: FilterKind,
+    #[reflect(@SliderRange(1.0, 500.0))]
+    #[builder(default = 100.0)]
+    pub face_measurement_noise: f32,
+    #[reflect(@SliderRange(0.01, 10.0))]
+    #[builder(default = 3.0)]
+    pub face_min_cutoff: f32,
+    #[reflect(@SliderRange(0.0, 20.0))]
+    #[builder(default = 1.0)]
+    pub face_beta: f32,
+    #[reflect(@SliderRange(0.0, 0.2))]
+    #[builder(default = 0.)]
+    pub face_deadband: f32,
+    /// Kalman process noises shared by every stream, how fast positions and velocities may change.
+    #[reflect(@SliderRange(0.0, 10.0))]
+    #[builder(default = 1.0)]
+    pub kalman_position_noise: f32,
+    #[reflect(@SliderRange(0.0, 20.0))]
+    #[builder(default = 3.0)]
+    pub kalman_velocity_noise: f32,
+
+    /// Seconds the pose and hands are extrapolated past the time since their last frame.
+    #[reflect(@Separator)]
+    #[reflect(@SliderRange(0.0, 0.2))]
+    #[builder(default = 0.05)]
+    pub look_ahead: f32,
+    #[reflect(@SliderRange(0.0, 0.3))]
+    #[builder(default = 0.1)]
+    pub max_extrapolation: f32,
+
+    #[reflect(@Separator)]
+    #[builder(default = false)]
+    pub record_session: bool,
+    #[builder(default = false)]
+    pub record_take: bool,
+    #[builder(default = true)]
+    pub export_gltf: bool,
+    #[builder(default = true)]
+    pub export_bvh: bool,
+    #[reflect(@SliderRange(10.0, 120.0))]
+    #[builder(default = 30.)]
+    pub bvh_frame_rate: f32,
 
     #[reflect(@Separator)]
     #[builder(default = true)]
@@ -47,10 +136,34 @@ pub struct GuiState {
     #[builder(default = 0.9)]
     pub edge: f32,
 
+    /// Measures the limb lengths while on, turns itself off when done.
+    #[reflect(@Separator)]
+    #[builder(default = false)]
+    pub calibrate_bone_lengths: bool,
+    /// Refits the tracked limbs to the calibrated lengths, so a badly tracked elbow or knee does
+    /// not bend them more or less than the hand or foot allows.
+    #[builder(default = true)]
+    pub constrain_bone_lengths: bool,
+
     #[reflect(@Separator)]
     #[builder(default = true)]
     pub rotate_root: bool,
     #[builder(default = true)]
+    pub rotate_spine: bool,
+    /// Shares of the torso's bend and twist, over whichever of these bones the model has.
+    #[reflect(@SliderRange(0.0, 1.0))]
+    #[builder(default = 0.2)]
+    pub hips_weight: f32,
+    #[reflect(@SliderRange(0.0, 1.0))]
+    #[builder(default = 0.3)]
+    pub spine_weight: f32,
+    #[reflect(@SliderRange(0.0, 1.0))]
+    #[builder(default = 0.3)]
+    pub chest_weight: f32,
+    #[reflect(@SliderRange(0.0, 1.0))]
+    #[builder(default = 0.2)]
+    pub upper_chest_weight: f32,
+    #[builder(default = true)]
     pub rotate_neck: bool,
     #[builder(default = true)]
     pub rotate_left_upper_arm: bool,
@@ -76,9 +189,120 @@ pub struct GuiState {
     #[builder(default = true)]
     pub rotate_right_lower_leg: bool,
 
+    /// FK aims each bone along its tracked segment, IK lands the hand or foot on the tracked
+    /// wrist or ankle.
+    #[reflect(@Separator)]
+    #[builder(default = LimbSolver::Fk)]
+    pub left_arm_solver: LimbSolver,
+    #[builder(default = LimbSolver::Fk)]
+    pub right_arm_solver: LimbSolver,
+    #[builder(default = LimbSolver::Fk)]
+    pub left_leg_solver: LimbSolver,
+    #[builder(default = LimbSolver::Fk)]
+    pub right_leg_solver: LimbSolver,
+
+    /// Pins grounded feet in place through leg IK.
+    #[reflect(@Separator)]
+    #[builder(default = true)]
+    pub lock_feet: bool,
+    /// Height in metres above the floor under which a foot can be grounded.
+    #[reflect(@SliderRange(0.0, 0.2))]
+    #[builder(default = 0.05)]
+    pub contact_height: f32,
+    /// Speed in metres per second under which a foot can be grounded.
+    #[reflect(@SliderRange(0.0, 1.0))]
+    #[builder(default = 0.3)]
+    pub contact_speed: f32,
+    /// Height of the floor feet are grounded on, the root is raised so no foot sinks below it.
+    #[reflect(@SliderRange(-2.0, 2.0))]
+    #[builder(default = 0.0)]
+    pub floor_height: f32,
+
+    /// Drives the avatar from a VMC performer sending to this UDP port, also `--vmc-port`.
+    #[reflect(@Separator)]
+    #[builder(default = true)]
+    pub vmc_receive: bool,
+    #[builder(default = 39539)]
+    pub vmc_receive_port: u16,
+    /// Sends the avatar's pose to another VMC application, also `--vmc-send host:port`.
+    #[builder(default = false)]
+    pub vmc_send: bool,
+    #[builder(default = "127.0.0.1".to_string())]
+    pub vmc_send_host: String,
+    /// 39540 by default, so it does not loop back into our own receiver.
+    #[builder(default = 39540)]
+    pub vmc_send_port: u16,
+    /// Packets sent per second.
+    #[reflect(@SliderRange(1.0, 120.0))]
+    #[builder(default = 60.0)]
+    pub vmc_send_rate: f32,
+
     #[reflect(@Separator)]
     #[builder(default = true)]
     pub rotate_thumb_cmp: bool,
     #[builder(default = true)]
     pub rotate_index_cmp: bool,
 }
+
+/// Same defaults as the builder, so headless runs read the values the panel starts with.
+impl Default for GuiState {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl GuiState {
+    pub fn pose_filter_config(&self) -> FilterConfig {
+        FilterConfig {
+            gate: self.pose_gate,
+            kind: self.pose_filter,
+            position_noise: self.kalman_position_noise,
+            velocity_noise: self.kalman_velocity_noise,
+            measurement_noise: self.pose_measurement_noise,
+            min_cutoff: self.pose_min_cutoff,
+            beta: self.pose_beta,
+            deadband: self.pose_deadband,
+        }
+    }
+
+    pub fn hands_filter_config(&self) -> FilterConfig {
+        FilterConfig {
+            gate: self.hands_gate,
+            kind: self.hands_filter,
+            position_noise: self.kalman_position_noise,
+            velocity_noise: self.kalman_velocity_noise,
+            measurement_noise: self.hands_measurement_noise,
+            min_cutoff: self.hands_min_cutoff,
+            beta: self.hands_beta,
+            deadband: self.hands_deadband,
+        }
+    }
+
+    pub fn face_filter_config(&self) -> FilterConfig {
+        FilterConfig {
+            gate: self.face_gate,
+            kind: self.face_filter,
+            position_noise: self.kalman_position_noise,
+            velocity_noise: self.kalman_velocity_noise,
+            measurement_noise: self.face_measurement_noise,
+            min_cutoff: self.face_min_cutoff,
+            beta: self.face_beta,
+            deadband: self.face_deadband,
+        }
+    }
+
+    /// The frame the web client's MediaPipe landmarks, live or recorded, are in.
+    pub fn mediapipe_frame(&self) -> CoordinateFrame {
+        CoordinateFrame {
+            mirror: self.mirror_camera,
+            ..CoordinateFrame::MEDIAPIPE
+        }
+    }
+
+    pub fn prediction_config(&self) -> PredictionConfig {
+        PredictionConfig {
+            look_ahead: self.look_ahead,
+            max_extrapolation: self.max_extrapolation,
+        }
+    }
+}
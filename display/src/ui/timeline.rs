@@ -0,0 +1,191 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+use bevy_simple_subsecond_system::prelude::*;
+
+use crate::bvh::player::BvhPlayer;
+use crate::session::recordings_dir;
+use crate::session::playback::SessionPlayer;
+use crate::ui::slider::AdwSlider;
+use crate::ui::toggle_switch::toggle;
+
+#[derive(Default)]
+pub struct TimelinePanel {
+    /// Listed lazily, and again when "Refresh" is clicked.
+    recordings: Option<Vec<PathBuf>>,
+    selected: Option<PathBuf>,
+    error: Option<String>,
+}
+
+/// Transport controls shared by recorded sessions and BVH clips.
+trait Transport {
+    fn position(&self) -> f64;
+    fn duration(&self) -> f64;
+    fn seek(&mut self, position: f64);
+    fn state(&mut self) -> (&mut bool, &mut bool, &mut f32);
+}
+
+impl Transport for SessionPlayer {
+    fn position(&self) -> f64 {
+        self.position()
+    }
+
+    fn duration(&self) -> f64 {
+        self.duration()
+    }
+
+    fn seek(&mut self, position: f64) {
+        self.seek(position)
+    }
+
+    fn state(&mut self) -> (&mut bool, &mut bool, &mut f32) {
+        (&mut self.playing, &mut self.looping, &mut self.speed)
+    }
+}
+
+impl Transport for BvhPlayer {
+    fn position(&self) -> f64 {
+        self.position()
+    }
+
+    fn duration(&self) -> f64 {
+        self.duration()
+    }
+
+    fn seek(&mut self, position: f64) {
+        self.seek(position)
+    }
+
+    fn state(&mut self) -> (&mut bool, &mut bool, &mut f32) {
+        (&mut self.playing, &mut self.looping, &mut self.speed)
+    }
+}
+
+#[hot]
+pub fn timeline_ui(
+    mut contexts: EguiContexts,
+    mut player: ResMut<SessionPlayer>,
+    mut bvh_player: ResMut<BvhPlayer>,
+    mut panel: Local<TimelinePanel>,
+) {
+    let panel = &mut *panel;
+    let recordings = panel.recordings.get_or_insert_with(list_recordings);
+
+    egui::Window::new("Timeline").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            let selected_text = panel
+                .selected
+                .as_deref()
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Select a recording".to_string());
+
+            egui::ComboBox::from_id_salt("timeline_recording")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    for path in recordings.iter() {
+                        let name = path
+                            .file_name()
+                            .map(|n| n.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        ui.selectable_value(&mut panel.selected, Some(path.clone()), name);
+                    }
+                });
+
+            if ui.button("Refresh").clicked() {
+                *recordings = list_recordings();
+            }
+
+            if ui.button("Load").clicked() {
+                if let Some(path) = &panel.selected {
+                    // Only one recorded source drives the avatar at a time.
+                    let loaded = if is_bvh(path) {
+                        player.unload();
+                        bvh_player.load(path)
+                    } else {
+                        bvh_player.unload();
+                        player.load(path)
+                    };
+                    match loaded {
+                        Ok(frames) => {
+                            info!("Loaded {} frames from {}", frames, path.display());
+                            panel.error = None;
+                        }
+                        Err(err) => {
+                            error!("{}", err);
+                            panel.error = Some(err);
+                        }
+                    }
+                }
+            }
+
+            if (player.is_loaded() || bvh_player.is_loaded()) && ui.button("Back to live").clicked()
+            {
+                player.unload();
+                bvh_player.unload();
+            }
+        });
+
+        if let Some(err) = &panel.error {
+            ui.colored_label(egui::Color32::RED, err);
+        }
+
+        if player.is_loaded() {
+            transport_ui(ui, &mut *player);
+        } else if bvh_player.is_loaded() {
+            transport_ui(ui, &mut *bvh_player);
+        } else {
+            ui.label("Live input");
+        }
+    });
+}
+
+fn transport_ui(ui: &mut egui::Ui, player: &mut impl Transport) {
+    let position = player.position();
+    let duration = player.duration();
+    let (playing, looping, speed) = player.state();
+
+    ui.horizontal(|ui| {
+        let label = if *playing { "Pause" } else { "Play" };
+        if ui.button(label).clicked() {
+            *playing = !*playing;
+        }
+
+        ui.label("Loop");
+        ui.add(toggle(looping));
+
+        ui.label(format!("{:.2} / {:.2} s", position, duration));
+    });
+
+    ui.horizontal(|ui| {
+        ui.label(format!("Speed: {:.2}x", speed));
+        ui.add(AdwSlider::new(speed, 0.1..=4.0));
+    });
+
+    let mut seek = position as f32;
+    ui.add(AdwSlider::new(
+        &mut seek,
+        0.0..=(duration as f32).max(f32::EPSILON),
+    ));
+    if seek != position as f32 {
+        player.seek(seek as f64);
+    }
+}
+
+fn is_bvh(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "bvh")
+}
+
+fn list_recordings() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(recordings_dir()) else {
+        return Vec::new();
+    };
+    let mut recordings: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| is_bvh(path) || path.extension().is_some_and(|ext| ext == "ndjson"))
+        .collect();
+    recordings.sort();
+    recordings
+}
@@ -2,6 +2,7 @@ use bevy::color::palettes::css::RED;
 use bevy::core_pipeline::prepass::{DepthPrepass, NormalPrepass};
 use bevy::core_pipeline::tonemapping::Tonemapping;
 use bevy::prelude::*;
+use bevy::reflect::{DynamicEnum, DynamicVariant, Enum, ReflectMut, TypeInfo};
 use bevy::render::render_resource::{Extent3d, TextureDescriptor, TextureDimension, TextureFormat};
 use bevy::render::{camera::RenderTarget, render_resource::TextureUsages};
 use bevy::{
@@ -10,13 +11,18 @@ use bevy::{
 };
 use bevy_egui::{EguiPlugin, EguiUserTextures, egui};
 
+use crate::api::face_api::CurrentFace;
+use crate::api::hands_api::CurrentHands;
+use crate::api::pose_api::CurrentPose;
 use crate::camera_controller::CameraController;
 use crate::character_control::mouth::MouthOverlay;
 // use crate::gizmos_plugin::MouthOverlay;
 use crate::material::post_processing_moebius::MoebiusPostProcessSettings;
+use crate::math::filter::FilterChain;
 // use crate::material::post_processing_plugin::PostProcessSettings;
 use crate::ui::slider::AdwSlider;
 use crate::ui::theme::adw_colors;
+use crate::ui::timeline::timeline_ui;
 use crate::ui::toggle_switch::toggle;
 use bevy_egui::egui::Stroke;
 use bevy_egui::egui::{FontId, RichText, TextStyle};
@@ -43,14 +49,17 @@ pub struct GuiControllerPlugin;
 
 impl Plugin for GuiControllerPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource::<GuiState>(GuiState::builder().build())
+        app.init_resource::<GuiState>()
             .init_resource::<SceneViewFocus>();
         app.add_plugins(EguiPlugin {
             enable_multipass_for_primary_context: true,
         });
 
         app.add_event::<ResizeScenePreview>();
-        app.add_systems(EguiContextPass, (ui_system, render_to_image_system));
+        app.add_systems(
+            EguiContextPass,
+            (ui_system, render_to_image_system, timeline_ui),
+        );
         app.add_systems(PostUpdate, resize_scene_texture_system);
         app.add_systems(Startup, setup_system);
     }
@@ -290,7 +299,25 @@ fn customize_egui_theme(ctx: &egui::Context) {
 }
 
 #[hot]
-fn ui_system(mut contexts: EguiContexts, mut state: ResMut<GuiState>) {
+fn ui_system(
+    mut contexts: EguiContexts,
+    mut state: ResMut<GuiState>,
+    current_pose: Res<CurrentPose>,
+    current_hands: Res<CurrentHands>,
+    current_face: Res<CurrentFace>,
+) {
+    // Frames the outlier gates replaced with their prediction
+    let rejected_pose = current_pose.0.as_ref().map_or(0, FilterChain::rejected_frames);
+    let rejected_hands = [&current_hands.left_hand, &current_hands.right_hand]
+        .into_iter()
+        .flatten()
+        .map(FilterChain::rejected_frames)
+        .sum::<u64>();
+    let rejected_face = current_face
+        .filter
+        .as_ref()
+        .map_or(0, FilterChain::rejected_frames);
+
     let ctx = contexts.ctx_mut();
 
     // Only initialize fonts once
@@ -412,7 +439,21 @@ fn ui_system(mut contexts: EguiContexts, mut state: ResMut<GuiState>) {
                                         };
                                         label_slider(ui, &title_name, value, range);
                                     }
-                                    // You could add more types here (e.g., for strings, enums, etc.)
+                                    else if let Some(value) =
+                                        field_value.try_downcast_mut::<String>()
+                                    {
+                                        label_text(ui, &title_name, value);
+                                    } else if let Some(value) =
+                                        field_value.try_downcast_mut::<u16>()
+                                    {
+                                        label_number(ui, &title_name, value);
+                                    }
+                                    // Unit-only enums get a drop-down of their variants.
+                                    else if let ReflectMut::Enum(value) = field_value.reflect_mut()
+                                    {
+                                        label_combo(ui, &title_name, value);
+                                    }
+                                    // You could add more types here (e.g., for strings, etc.)
                                     else {
                                         ui.label(format!("{}: (Unsupported Type)", field_name));
                                     }
@@ -425,6 +466,13 @@ fn ui_system(mut contexts: EguiContexts, mut state: ResMut<GuiState>) {
 
                             ui.separator();
 
+                            ui.label(format!(
+                                "Rejected frames: pose {}, hands {}, face {}",
+                                rejected_pose, rejected_hands, rejected_face
+                            ));
+
+                            ui.separator();
+
                             ui.label(format!(
                                 "\
 Freecam Controls:\n\
@@ -483,6 +531,58 @@ fn label_slider(
         });
     });
 }
+
+fn label_text(ui: &mut egui::Ui, label: &str, value: &mut String) {
+    ui.horizontal(|ui| {
+        ui.set_min_height(32.0);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label(label);
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.text_edit_singleline(value);
+        });
+    });
+}
+
+fn label_number(ui: &mut egui::Ui, label: &str, value: &mut u16) {
+    ui.horizontal(|ui| {
+        ui.set_min_height(32.0);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label(label);
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.add(egui::DragValue::new(value));
+        });
+    });
+}
+
+fn label_combo(ui: &mut egui::Ui, label: &str, value: &mut dyn Enum) {
+    let Some(TypeInfo::Enum(info)) = value.get_represented_type_info() else {
+        return;
+    };
+    let mut selected = value.variant_name().to_string();
+
+    ui.horizontal(|ui| {
+        ui.set_min_height(32.0);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label(label);
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            egui::ComboBox::from_id_salt(label)
+                .selected_text(selected.clone())
+                .show_ui(ui, |ui| {
+                    for name in info.variant_names() {
+                        ui.selectable_value(&mut selected, name.to_string(), *name);
+                    }
+                });
+        });
+    });
+
+    if selected != value.variant_name() {
+        value.apply(&DynamicEnum::new(selected, DynamicVariant::Unit));
+    }
+}
+
 fn to_title_case(s: &str) -> String {
     s.replace('_', " ")
         .split_whitespace()
@@ -0,0 +1,3 @@
+pub mod osc;
+pub mod receiver;
+pub mod sender;
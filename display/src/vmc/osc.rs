@@ -0,0 +1,375 @@
+//! Minimal OSC 1.0 packet encoding and decoding, enough for the VMC protocol.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    String(String),
+    Blob(Vec<u8>),
+    Long(i64),
+    Double(f64),
+    Time(u64),
+    Bool(bool),
+    Nil,
+    Impulse,
+}
+
+impl OscArg {
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            OscArg::Float(v) => Some(*v),
+            OscArg::Double(v) => Some(*v as f32),
+            OscArg::Int(v) => Some(*v as f32),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            OscArg::String(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscPacket {
+    Message(OscMessage),
+    Bundle {
+        time_tag: u64,
+        content: Vec<OscPacket>,
+    },
+}
+
+impl OscPacket {
+    /// Calls `f` for every message in the packet, flattening nested bundles.
+    pub fn for_each_message(&self, f: &mut impl FnMut(&OscMessage)) {
+        match self {
+            OscPacket::Message(message) => f(message),
+            OscPacket::Bundle { content, .. } => {
+                for packet in content {
+                    packet.for_each_message(f);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OscError {
+    #[error("Packet ended unexpectedly")]
+    UnexpectedEnd,
+    #[error("String is not valid UTF-8")]
+    InvalidString,
+    #[error("Missing type tag string")]
+    MissingTypeTags,
+    #[error("Unsupported type tag '{0}'")]
+    UnsupportedType(char),
+    #[error("Address '{0}' does not start with '/'")]
+    InvalidAddress(String),
+    #[error("Bundles are nested deeper than {MAX_BUNDLE_DEPTH}")]
+    TooDeep,
+}
+
+const BUNDLE_TAG: &[u8] = b"#bundle\0";
+
+/// How deep bundles may be nested, so a crafted packet can't recurse until the stack overflows.
+const MAX_BUNDLE_DEPTH: usize = 8;
+
+pub fn decode(buf: &[u8]) -> Result<OscPacket, OscError> {
+    decode_packet(buf, 0)
+}
+
+fn decode_packet(buf: &[u8], depth: usize) -> Result<OscPacket, OscError> {
+    if buf.starts_with(BUNDLE_TAG) {
+        decode_bundle(buf, depth)
+    } else {
+        decode_message(buf).map(OscPacket::Message)
+    }
+}
+
+fn decode_bundle(buf: &[u8], depth: usize) -> Result<OscPacket, OscError> {
+    if depth >= MAX_BUNDLE_DEPTH {
+        return Err(OscError::TooDeep);
+    }
+    let mut reader = Reader::new(&buf[BUNDLE_TAG.len()..]);
+    let time_tag = reader.u64()?;
+    let mut content = Vec::new();
+    while !reader.is_empty() {
+        let size = reader.i32()?;
+        let size = usize::try_from(size).map_err(|_| OscError::UnexpectedEnd)?;
+        content.push(decode_packet(reader.take(size)?, depth + 1)?);
+    }
+    Ok(OscPacket::Bundle { time_tag, content })
+}
+
+fn decode_message(buf: &[u8]) -> Result<OscMessage, OscError> {
+    let mut reader = Reader::new(buf);
+    let address = reader.string()?;
+    if !address.starts_with('/') {
+        return Err(OscError::InvalidAddress(address));
+    }
+
+    // Very old senders may omit the type tag string entirely.
+    if reader.is_empty() {
+        return Ok(OscMessage {
+            address,
+            args: Vec::new(),
+        });
+    }
+    let tags = reader.string()?;
+    let tags = tags.strip_prefix(',').ok_or(OscError::MissingTypeTags)?;
+
+    let mut args = Vec::with_capacity(tags.len());
+    for tag in tags.chars() {
+        let arg = match tag {
+            'i' => OscArg::Int(reader.i32()?),
+            'f' => OscArg::Float(f32::from_bits(reader.u32()?)),
+            's' | 'S' => OscArg::String(reader.string()?),
+            'b' => OscArg::Blob(reader.blob()?),
+            'h' => OscArg::Long(reader.u64()? as i64),
+            'd' => OscArg::Double(f64::from_bits(reader.u64()?)),
+            't' => OscArg::Time(reader.u64()?),
+            'T' => OscArg::Bool(true),
+            'F' => OscArg::Bool(false),
+            'N' => OscArg::Nil,
+            'I' => OscArg::Impulse,
+            other => return Err(OscError::UnsupportedType(other)),
+        };
+        args.push(arg);
+    }
+
+    Ok(OscMessage { address, args })
+}
+
+pub fn encode(packet: &OscPacket) -> Vec<u8> {
+    let mut buf = Vec::new();
+    encode_into(packet, &mut buf);
+    buf
+}
+
+fn encode_into(packet: &OscPacket, buf: &mut Vec<u8>) {
+    match packet {
+        OscPacket::Message(message) => encode_message(message, buf),
+        OscPacket::Bundle { time_tag, content } => {
+            buf.extend_from_slice(BUNDLE_TAG);
+            buf.extend_from_slice(&time_tag.to_be_bytes());
+            for packet in content {
+                let size_at = buf.len();
+                buf.extend_from_slice(&[0; 4]);
+                encode_into(packet, buf);
+                let size = (buf.len() - size_at - 4) as u32;
+                buf[size_at..size_at + 4].copy_from_slice(&size.to_be_bytes());
+            }
+        }
+    }
+}
+
+fn encode_message(message: &OscMessage, buf: &mut Vec<u8>) {
+    write_string(&message.address, buf);
+
+    let mut tags = String::with_capacity(message.args.len() + 1);
+    tags.push(',');
+    for arg in &message.args {
+        tags.push(match arg {
+            OscArg::Int(_) => 'i',
+            OscArg::Float(_) => 'f',
+            OscArg::String(_) => 's',
+            OscArg::Blob(_) => 'b',
+            OscArg::Long(_) => 'h',
+            OscArg::Double(_) => 'd',
+            OscArg::Time(_) => 't',
+            OscArg::Bool(true) => 'T',
+            OscArg::Bool(false) => 'F',
+            OscArg::Nil => 'N',
+            OscArg::Impulse => 'I',
+        });
+    }
+    write_string(&tags, buf);
+
+    for arg in &message.args {
+        match arg {
+            OscArg::Int(v) => buf.extend_from_slice(&v.to_be_bytes()),
+            OscArg::Float(v) => buf.extend_from_slice(&v.to_be_bytes()),
+            OscArg::String(v) => write_string(v, buf),
+            OscArg::Blob(v) => {
+                buf.extend_from_slice(&(v.len() as i32).to_be_bytes());
+                buf.extend_from_slice(v);
+                buf.resize(padded_len(buf.len()), 0);
+            }
+            OscArg::Long(v) => buf.extend_from_slice(&v.to_be_bytes()),
+            OscArg::Double(v) => buf.extend_from_slice(&v.to_be_bytes()),
+            OscArg::Time(v) => buf.extend_from_slice(&v.to_be_bytes()),
+            OscArg::Bool(_) | OscArg::Nil | OscArg::Impulse => {}
+        }
+    }
+}
+
+/// Writes a null-terminated string padded to a multiple of 4 bytes.
+fn write_string(value: &str, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(0);
+    buf.resize(padded_len(buf.len()), 0);
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], OscError> {
+        if self.buf.len() < len {
+            return Err(OscError::UnexpectedEnd);
+        }
+        let (head, tail) = self.buf.split_at(len);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, OscError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn i32(&mut self) -> Result<i32, OscError> {
+        self.u32().map(|v| v as i32)
+    }
+
+    fn u64(&mut self) -> Result<u64, OscError> {
+        let high = self.u32()? as u64;
+        let low = self.u32()? as u64;
+        Ok((high << 32) | low)
+    }
+
+    /// Reads a null-terminated string padded to a multiple of 4 bytes.
+    fn string(&mut self) -> Result<String, OscError> {
+        let len = self
+            .buf
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(OscError::UnexpectedEnd)?;
+        let bytes = self.take(padded_len(len + 1))?;
+        String::from_utf8(bytes[..len].to_vec()).map_err(|_| OscError::InvalidString)
+    }
+
+    fn blob(&mut self) -> Result<Vec<u8>, OscError> {
+        let len = usize::try_from(self.i32()?).map_err(|_| OscError::UnexpectedEnd)?;
+        let bytes = self.take(padded_len(len))?;
+        Ok(bytes[..len].to_vec())
+    }
+}
+
+fn padded_len(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(address: &str, args: Vec<OscArg>) -> OscPacket {
+        OscPacket::Message(OscMessage {
+            address: address.to_string(),
+            args,
+        })
+    }
+
+    #[test]
+    fn message_round_trip() {
+        let packet = message(
+            "/VMC/Ext/Bone/Pos",
+            vec![
+                OscArg::Int(-7),
+                OscArg::Float(1.5),
+                // Lengths around the 4 byte padding
+                OscArg::String("abc".to_string()),
+                OscArg::String("abcd".to_string()),
+                OscArg::Blob(vec![1, 2, 3, 4, 5]),
+                OscArg::Long(-1 << 40),
+                OscArg::Double(0.25),
+                OscArg::Time(1),
+                OscArg::Bool(true),
+                OscArg::Bool(false),
+                OscArg::Nil,
+                OscArg::Impulse,
+            ],
+        );
+        let encoded = encode(&packet);
+        assert_eq!(encoded.len() % 4, 0);
+        assert_eq!(decode(&encoded).unwrap(), packet);
+    }
+
+    #[test]
+    fn nested_bundle_round_trip() {
+        let packet = OscPacket::Bundle {
+            time_tag: 1,
+            content: vec![
+                message("/VMC/Ext/OK", vec![OscArg::Int(1)]),
+                OscPacket::Bundle {
+                    time_tag: 2,
+                    content: vec![
+                        message(
+                            "/VMC/Ext/Blend/Val",
+                            vec![OscArg::String("A".to_string()), OscArg::Float(0.5)],
+                        ),
+                        OscPacket::Bundle {
+                            time_tag: 3,
+                            content: Vec::new(),
+                        },
+                    ],
+                },
+                message("/VMC/Ext/Blend/Apply", Vec::new()),
+            ],
+        };
+        assert_eq!(decode(&encode(&packet)).unwrap(), packet);
+
+        let mut addresses = Vec::new();
+        packet.for_each_message(&mut |message| addresses.push(message.address.clone()));
+        assert_eq!(
+            addresses,
+            ["/VMC/Ext/OK", "/VMC/Ext/Blend/Val", "/VMC/Ext/Blend/Apply"]
+        );
+    }
+
+    #[test]
+    fn deep_bundles_are_rejected() {
+        let nested = |depth: usize| {
+            (0..depth).fold(message("/a", Vec::new()), |packet, _| OscPacket::Bundle {
+                time_tag: 1,
+                content: vec![packet],
+            })
+        };
+        assert!(decode(&encode(&nested(MAX_BUNDLE_DEPTH))).is_ok());
+        assert!(matches!(
+            decode(&encode(&nested(MAX_BUNDLE_DEPTH + 1))),
+            Err(OscError::TooDeep)
+        ));
+    }
+
+    #[test]
+    fn truncated_packets_are_errors() {
+        let encoded = encode(&message("/VMC/Ext/OK", vec![OscArg::Int(1)]));
+        // Cut inside the address, the type tags and the argument
+        for len in [2, 14, encoded.len() - 1] {
+            assert!(decode(&encoded[..len]).is_err());
+        }
+        assert!(matches!(
+            decode(&encode(&message("no-slash", Vec::new()))),
+            Err(OscError::InvalidAddress(_))
+        ));
+    }
+}
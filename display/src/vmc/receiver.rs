@@ -0,0 +1,327 @@
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::prelude::*;
+
+use crate::api::face_api::{CurrentFace, FaceExpression, VISEMES};
+use crate::character_control::character_controller::CharacterParts;
+use crate::character_control::humanoid::{HumanoidBone, RestPose};
+use crate::character_control::joint_limits::JointLimits;
+use crate::character_control::rotate_body::rotate_body;
+use crate::character_control::rotate_hands::rotate_hands;
+use crate::math::coordinate_frame::CoordinateFrame;
+use crate::ui::state::GuiState;
+use crate::vmc::osc::{self, OscMessage};
+
+/// Receives Virtual Motion Capture (OSC over UDP) data and drives the avatar with it.
+///
+/// Bones and blendshapes received from a VMC performer override the MediaPipe driven pose for as
+/// long as packets keep arriving. Try it with any VMC sender pointed at `localhost:39539`.
+pub struct VmcReceiverPlugin;
+
+impl Plugin for VmcReceiverPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<VmcReceiverConfig>()
+            .init_resource::<VmcReceiverConfig>()
+            .init_resource::<VmcReceiverState>()
+            .add_systems(
+                Update,
+                (
+                    configure_vmc_receiver.run_if(resource_changed::<GuiState>),
+                    bind_vmc_socket,
+                    receive_vmc,
+                    apply_vmc_bones.after(rotate_body).after(rotate_hands),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct VmcReceiverConfig {
+    pub enabled: bool,
+    pub port: u16,
+    /// How long the last received bones keep overriding the avatar after packets stop.
+    pub timeout: Duration,
+    /// The space the performer sends bones in, Unity's for every VMC application we know of.
+    pub frame: CoordinateFrame,
+}
+
+impl Default for VmcReceiverConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            port: 39539,
+            timeout: Duration::from_millis(500),
+            frame: CoordinateFrame::UNITY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VmcBonePose {
+    pub position: Vec3,
+    pub rotation: Quat,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct VmcReceiverState {
+    socket: Option<UdpSocket>,
+    bound_port: Option<u16>,
+    pub root: Option<VmcBonePose>,
+    pub bones: HashMap<HumanoidBone, VmcBonePose>,
+    /// Blendshape values received since the last `/VMC/Ext/Blend/Apply`.
+    pub pending_blendshapes: HashMap<String, f32>,
+    pub last_packet: Option<Instant>,
+}
+
+impl VmcReceiverState {
+    fn is_live(&self, timeout: Duration) -> bool {
+        self.last_packet
+            .is_some_and(|last| last.elapsed() < timeout)
+    }
+}
+
+/// Takes the settings of the receiver from [`GuiState::vmc_receive`] and the fields after it.
+fn configure_vmc_receiver(gui_state: Res<GuiState>, mut config: ResMut<VmcReceiverConfig>) {
+    config.enabled = gui_state.vmc_receive;
+    config.port = gui_state.vmc_receive_port;
+}
+
+fn bind_vmc_socket(config: Res<VmcReceiverConfig>, mut state: ResMut<VmcReceiverState>) {
+    if !config.enabled {
+        if state.socket.is_some() {
+            info!("VMC receiver stopped");
+            state.socket = None;
+            state.bound_port = None;
+        }
+        return;
+    }
+
+    if state.bound_port == Some(config.port) {
+        return;
+    }
+
+    state.socket = None;
+    // Remember the attempt either way, so a busy port is not retried every frame.
+    state.bound_port = Some(config.port);
+
+    match UdpSocket::bind(("0.0.0.0", config.port)).and_then(|socket| {
+        socket.set_nonblocking(true)?;
+        Ok(socket)
+    }) {
+        Ok(socket) => {
+            info!("VMC receiver listening on UDP port {}", config.port);
+            state.socket = Some(socket);
+        }
+        Err(err) => {
+            error!(
+                "Failed to bind VMC receiver to port {}: {}",
+                config.port, err
+            );
+        }
+    }
+}
+
+#[hot]
+fn receive_vmc(
+    config: Res<VmcReceiverConfig>,
+    mut state: ResMut<VmcReceiverState>,
+    mut current_face: ResMut<CurrentFace>,
+) {
+    let Some(socket) = state.socket.take() else {
+        return;
+    };
+
+    let mut buf = [0u8; 65536];
+    loop {
+        let len = match socket.recv_from(&mut buf) {
+            Ok((len, _)) => len,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+            Err(err) => {
+                warn!("VMC receive error: {}", err);
+                break;
+            }
+        };
+
+        match osc::decode(&buf[..len]) {
+            Ok(packet) => {
+                state.last_packet = Some(Instant::now());
+                packet.for_each_message(&mut |message| {
+                    handle_vmc_message(message, &config.frame, &mut state, &mut current_face)
+                });
+            }
+            Err(err) => warn!("Invalid VMC packet: {}", err),
+        }
+    }
+
+    state.socket = Some(socket);
+}
+
+fn handle_vmc_message(
+    message: &OscMessage,
+    frame: &CoordinateFrame,
+    state: &mut VmcReceiverState,
+    current_face: &mut CurrentFace,
+) {
+    match message.address.as_str() {
+        "/VMC/Ext/Root/Pos" => {
+            if let Some((_, pose)) = parse_bone_pose(message, frame) {
+                state.root = Some(pose);
+            }
+        }
+        "/VMC/Ext/Bone/Pos" => {
+            if let Some((name, pose)) = parse_bone_pose(message, frame) {
+                // Bones we have no HumanoidBone for (Head, toes, thumb proximals, ...) are ignored.
+                if let Ok(bone) = HumanoidBone::from_str(name) {
+                    state.bones.insert(bone, pose);
+                }
+            }
+        }
+        "/VMC/Ext/Blend/Val" => {
+            let name = message.args.first().and_then(|a| a.as_str());
+            let value = message.args.get(1).and_then(|a| a.as_f32());
+            if let (Some(name), Some(value)) = (name, value) {
+                state.pending_blendshapes.insert(name.to_string(), value);
+            }
+        }
+        "/VMC/Ext/Blend/Apply" => {
+            let expression = current_face
+                .expression
+                .get_or_insert_with(FaceExpression::default);
+            for (name, value) in state.pending_blendshapes.drain() {
+                set_vmc_blendshape(expression, &name, value);
+            }
+            expression.update_look();
+        }
+        _ => {}
+    }
+}
+
+/// Parses `(name, px, py, pz, qx, qy, qz, qw)`, converting from the performer's `frame`.
+fn parse_bone_pose<'a>(
+    message: &'a OscMessage,
+    frame: &CoordinateFrame,
+) -> Option<(&'a str, VmcBonePose)> {
+    let name = message.args.first()?.as_str()?;
+    let mut values = [0.0f32; 7];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = message.args.get(i + 1)?.as_f32()?;
+    }
+    let [px, py, pz, qx, qy, qz, qw] = values;
+
+    Some((
+        name,
+        VmcBonePose {
+            position: frame.position(Vec3::new(px, py, pz)),
+            rotation: frame.rotation(Quat::from_xyzw(qx, qy, qz, qw).normalize()),
+        },
+    ))
+}
+
+/// Applies a VMC blendshape, which is either a VRM preset (`"Blink_L"`, `"A"`, ...) or an
+/// ARKit "perfect sync" name (`"EyeBlinkLeft"`).
+fn set_vmc_blendshape(expression: &mut FaceExpression, name: &str, value: f32) {
+    if let Some(viseme) = VISEMES.iter().find(|viseme| viseme.vmc == name) {
+        viseme.apply(expression, value);
+        return;
+    }
+    match name {
+        "Blink" => {
+            expression.eye_blink_left = value;
+            expression.eye_blink_right = value;
+        }
+        "Blink_L" => expression.eye_blink_left = value,
+        "Blink_R" => expression.eye_blink_right = value,
+        "Joy" => {
+            expression.mouth_smile_left = value;
+            expression.mouth_smile_right = value;
+        }
+        "Angry" => {
+            expression.brow_down_left = value;
+            expression.brow_down_right = value;
+        }
+        "Sorrow" => {
+            expression.brow_inner_up = value;
+            expression.mouth_frown_left = value;
+            expression.mouth_frown_right = value;
+        }
+        "Fun" => {
+            expression.cheek_squint_left = value;
+            expression.cheek_squint_right = value;
+        }
+        "LookUp" => {
+            expression.eye_look_up_left = value;
+            expression.eye_look_up_right = value;
+        }
+        "LookDown" => {
+            expression.eye_look_down_left = value;
+            expression.eye_look_down_right = value;
+        }
+        "LookLeft" => {
+            expression.eye_look_out_left = value;
+            expression.eye_look_in_right = value;
+        }
+        "LookRight" => {
+            expression.eye_look_in_left = value;
+            expression.eye_look_out_right = value;
+        }
+        _ => {
+            // Perfect sync names are the MediaPipe ones with an upper case first letter.
+            let mut chars = name.chars();
+            let camel_case = match chars.next() {
+                Some(first) => first.to_lowercase().collect::<String>() + chars.as_str(),
+                None => return,
+            };
+            expression.set_blendshape(&camel_case, value);
+        }
+    }
+}
+
+#[hot]
+fn apply_vmc_bones(
+    config: Res<VmcReceiverConfig>,
+    state: Res<VmcReceiverState>,
+    parts: Res<CharacterParts>,
+    rest_pose: Res<RestPose>,
+    joint_limits: Res<JointLimits>,
+    gui_state: Res<GuiState>,
+    mut transform_q: Query<&mut Transform>,
+) {
+    if !state.is_live(config.timeout) {
+        return;
+    }
+
+    for (bone, pose) in state.bones.iter() {
+        let Some(entity) = parts.bone(*bone) else {
+            continue;
+        };
+        let Ok(mut transform) = transform_q.get_mut(entity) else {
+            continue;
+        };
+        // VMC sends rotations relative to a T-posed humanoid, so apply them on top of our rest pose.
+        // Limited like tracked rotations, a performer's rig may allow more than the model does
+        let rest = rest_pose.rotation(entity);
+        transform.rotation = joint_limits.apply(entity, rest * pose.rotation, rest);
+    }
+
+    if let (Some(root), Some(root_entity)) = (state.root, parts.root) {
+        if let Ok(mut transform) = transform_q.get_mut(root_entity) {
+            if gui_state.move_root {
+                transform.translation = root.position * gui_state.move_scale;
+            }
+            // Composed with the received Hips, or the rest pose without one, never with the current
+            // rotation, which already holds last frame's root
+            let hips = state
+                .bones
+                .get(&HumanoidBone::Hips)
+                .map_or(Quat::IDENTITY, |hips| hips.rotation);
+            transform.rotation = root.rotation * rest_pose.rotation(root_entity) * hips;
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::prelude::*;
+
+use crate::api::face_api::{CurrentFace, VISEMES};
+use crate::character_control::character_controller::CharacterParts;
+use crate::character_control::humanoid::RestPose;
+use crate::math::coordinate_frame::CoordinateFrame;
+use crate::ui::state::GuiState;
+use crate::vmc::osc::{self, OscArg, OscMessage, OscPacket};
+
+/// Sends the final avatar pose and face as Virtual Motion Capture (OSC over UDP) messages, so
+/// mola can be used as the tracking source of another VMC capable renderer.
+pub struct VmcSenderPlugin;
+
+impl Plugin for VmcSenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<VmcSenderConfig>()
+            .init_resource::<VmcSenderConfig>()
+            .init_resource::<VmcSenderState>()
+            .add_systems(
+                Update,
+                configure_vmc_sender.run_if(resource_changed::<GuiState>),
+            )
+            // Runs after every Update system has written its local rotations.
+            .add_systems(PostUpdate, send_vmc);
+    }
+}
+
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct VmcSenderConfig {
+    pub enabled: bool,
+    pub host: String,
+    /// Defaults to 39540, so it does not loop back into our own receiver on 39539.
+    pub port: u16,
+    pub send_rate_hz: f32,
+    /// The space the receiving application expects bones in.
+    pub frame: CoordinateFrame,
+}
+
+impl Default for VmcSenderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "127.0.0.1".to_string(),
+            port: 39540,
+            send_rate_hz: 60.0,
+            frame: CoordinateFrame::UNITY,
+        }
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct VmcSenderState {
+    /// Connected to `target`.
+    socket: Option<UdpSocket>,
+    /// The host and port the socket was connected to, it is reconnected when the config changes.
+    target: Option<(String, u16)>,
+    last_sent: Option<Instant>,
+}
+
+/// Takes the settings of the sender from [`GuiState::vmc_send`] and the fields after it.
+fn configure_vmc_sender(gui_state: Res<GuiState>, mut config: ResMut<VmcSenderConfig>) {
+    config.enabled = gui_state.vmc_send;
+    config.host.clone_from(&gui_state.vmc_send_host);
+    config.port = gui_state.vmc_send_port;
+    config.send_rate_hz = gui_state.vmc_send_rate;
+}
+
+#[hot]
+fn send_vmc(
+    config: Res<VmcSenderConfig>,
+    mut state: ResMut<VmcSenderState>,
+    parts: Res<CharacterParts>,
+    rest_pose: Res<RestPose>,
+    current_face: Res<CurrentFace>,
+    time: Res<Time>,
+    transform_q: Query<&Transform>,
+) {
+    if !config.enabled {
+        state.socket = None;
+        return;
+    }
+
+    let now = Instant::now();
+    let interval = Duration::from_secs_f32(1.0 / config.send_rate_hz.max(1.0));
+    if state
+        .last_sent
+        .is_some_and(|last| now.duration_since(last) < interval)
+    {
+        return;
+    }
+    state.last_sent = Some(now);
+
+    let target = (config.host.clone(), config.port);
+    if state.target.as_ref() != Some(&target) {
+        state.socket = None;
+    }
+    if state.socket.is_none() {
+        // Connecting resolves the host once here rather than on every packet
+        match UdpSocket::bind(("0.0.0.0", 0))
+            .and_then(|socket| socket.connect(&target).map(|()| socket))
+        {
+            Ok(socket) => {
+                state.socket = Some(socket);
+                state.target = Some(target);
+            }
+            Err(err) => {
+                error!(
+                    "Failed to open VMC sender socket to {}:{}: {}",
+                    target.0, target.1, err
+                );
+                return;
+            }
+        }
+    }
+
+    let mut content = vec![
+        message("/VMC/Ext/OK", vec![OscArg::Int(1)]),
+        message("/VMC/Ext/T", vec![OscArg::Float(time.elapsed_secs())]),
+    ];
+
+    // The root only carries where the avatar is, its rotation goes out with the Hips bone below,
+    // rest relative like every other bone
+    if let Some(root) = parts.root.and_then(|e| transform_q.get(e).ok()) {
+        content.push(bone_message(
+            "/VMC/Ext/Root/Pos",
+            "root",
+            root.translation,
+            Quat::IDENTITY,
+            &config.frame,
+        ));
+    }
+
+    for (bone, entity) in parts.found_bones() {
+        let Ok(transform) = transform_q.get(entity) else {
+            continue;
+        };
+        // VMC expects rotations relative to a T-posed humanoid, so undo our rest pose.
+        let rotation = rest_pose.rotation(entity).inverse() * transform.rotation;
+        content.push(bone_message(
+            "/VMC/Ext/Bone/Pos",
+            &bone.to_string(),
+            transform.translation,
+            rotation,
+            &config.frame,
+        ));
+    }
+
+    if let Some(expression) = &current_face.expression {
+        for (name, value) in expression.blendshapes() {
+            let Some(name) = perfect_sync_name(name) else {
+                continue;
+            };
+            content.push(message(
+                "/VMC/Ext/Blend/Val",
+                vec![OscArg::String(name), OscArg::Float(value)],
+            ));
+        }
+        // Models without perfect sync blendshapes only have the preset visemes
+        for viseme in VISEMES {
+            content.push(message(
+                "/VMC/Ext/Blend/Val",
+                vec![
+                    OscArg::String(viseme.vmc.to_string()),
+                    OscArg::Float(viseme.weight(expression)),
+                ],
+            ));
+        }
+        content.push(message("/VMC/Ext/Blend/Apply", Vec::new()));
+    }
+
+    let Some(socket) = &state.socket else {
+        return;
+    };
+
+    // Several small bundles keep every datagram well under the UDP size limit.
+    for chunk in content.chunks(16) {
+        let packet = OscPacket::Bundle {
+            // "Immediately", as defined by the OSC spec.
+            time_tag: 1,
+            content: chunk.to_vec(),
+        };
+        if let Err(err) = socket.send(&osc::encode(&packet)) {
+            warn!("VMC send error: {}", err);
+            return;
+        }
+    }
+}
+
+fn message(address: &str, args: Vec<OscArg>) -> OscPacket {
+    OscPacket::Message(OscMessage {
+        address: address.to_string(),
+        args,
+    })
+}
+
+/// Builds `(name, px, py, pz, qx, qy, qz, qw)`, converting to the receiver's `frame`.
+fn bone_message(
+    address: &str,
+    name: &str,
+    position: Vec3,
+    rotation: Quat,
+    frame: &CoordinateFrame,
+) -> OscPacket {
+    let position = frame.to_source_position(position);
+    let rotation = frame.to_source_rotation(rotation);
+    message(
+        address,
+        vec![
+            OscArg::String(name.to_string()),
+            OscArg::Float(position.x),
+            OscArg::Float(position.y),
+            OscArg::Float(position.z),
+            OscArg::Float(rotation.x),
+            OscArg::Float(rotation.y),
+            OscArg::Float(rotation.z),
+            OscArg::Float(rotation.w),
+        ],
+    )
+}
+
+/// ARKit "perfect sync" names are the MediaPipe ones with an upper case first letter.
+fn perfect_sync_name(name: &str) -> Option<String> {
+    let mut chars = name.chars();
+    let first = chars.next().filter(char::is_ascii_lowercase)?;
+    Some(first.to_ascii_uppercase().to_string() + chars.as_str())
+}
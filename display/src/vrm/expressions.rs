@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+use bevy::render::mesh::morph::MorphWeights;
+
+use crate::api::face_api::{CurrentFace, FaceExpression, VISEMES};
+use crate::vrm::{Vrm, VrmExpression, VrmLookAtType, VrmNodes};
+
+/// Weights of the VRM preset expressions for a set of MediaPipe blendshapes.
+fn preset_weights(face: &FaceExpression) -> impl Iterator<Item = (&'static str, f32)> {
+    let avg = |a: f32, b: f32| (a + b) / 2.0;
+    [
+        ("blink", avg(face.eye_blink_left, face.eye_blink_right)),
+        ("blinkLeft", face.eye_blink_left),
+        ("blinkRight", face.eye_blink_right),
+        ("happy", avg(face.mouth_smile_left, face.mouth_smile_right)),
+        ("angry", avg(face.brow_down_left, face.brow_down_right)),
+        ("sad", avg(face.mouth_frown_left, face.mouth_frown_right)),
+        ("surprised", face.brow_inner_up),
+    ]
+    .into_iter()
+    .chain(
+        VISEMES
+            .iter()
+            .map(|viseme| (viseme.vrm, viseme.weight(face))),
+    )
+}
+
+/// Drives the morph targets of the VRM expressions from the tracked face.
+pub fn apply_vrm_expressions(
+    vrm: Res<Vrm>,
+    nodes: Res<VrmNodes>,
+    curr_face: Res<CurrentFace>,
+    mut weights_q: Query<&mut MorphWeights>,
+) {
+    let Some(face) = curr_face.expression.as_ref() else {
+        return;
+    };
+
+    let mut weights: Vec<(&str, f32)> = preset_weights(face).collect();
+    if let Some(look_at) = vrm
+        .look_at
+        .filter(|look_at| look_at.kind == VrmLookAtType::Expression)
+    {
+        let (up, down, left, right) = look_at.expression_weights(face.look_x, face.look_y);
+        weights.extend([
+            ("lookUp", up),
+            ("lookDown", down),
+            ("lookLeft", left),
+            ("lookRight", right),
+        ]);
+    }
+
+    // Several expressions may bind the same morph target, so reset them all before adding up
+    for expression in vrm.expressions.preset.values() {
+        for bind in &expression.morph_target_binds {
+            if let Some(target) = morph_weight(&nodes, &mut weights_q, bind.node, bind.index) {
+                *target = 0.0;
+            }
+        }
+    }
+    for (name, weight) in weights {
+        let Some(expression) = vrm.expressions.preset.get(name) else {
+            continue;
+        };
+        apply_expression(expression, weight, &nodes, &mut weights_q);
+    }
+}
+
+fn apply_expression(
+    expression: &VrmExpression,
+    weight: f32,
+    nodes: &VrmNodes,
+    weights_q: &mut Query<&mut MorphWeights>,
+) {
+    let weight = if expression.is_binary {
+        weight.round()
+    } else {
+        weight.clamp(0.0, 1.0)
+    };
+    for bind in &expression.morph_target_binds {
+        if let Some(target) = morph_weight(nodes, weights_q, bind.node, bind.index) {
+            *target += weight * bind.weight;
+        }
+    }
+}
+
+fn morph_weight<'a>(
+    nodes: &VrmNodes,
+    weights_q: &'a mut Query<&mut MorphWeights>,
+    node: usize,
+    index: usize,
+) -> Option<&'a mut f32> {
+    let entity = nodes.0.get(&node)?;
+    let weights = weights_q.get_mut(*entity).ok()?;
+    weights.into_inner().weights_mut().get_mut(index)
+}
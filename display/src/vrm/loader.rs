@@ -0,0 +1,44 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::gltf::{Gltf, GltfError, GltfLoader, GltfLoaderSettings};
+use bevy::image::CompressedImageFormats;
+use bevy::prelude::*;
+use bevy::render::renderer::RenderDevice;
+
+/// Loads `.vrm` files, which are binary glTF with extra extensions, as plain glTF.
+///
+/// The VRM extensions are read separately by [`crate::vrm::Vrm::load`].
+pub struct VrmLoader(GltfLoader);
+
+impl VrmLoader {
+    /// Supports the same compressed textures as Bevy's own glTF loader.
+    pub fn new(app: &App) -> Self {
+        let supported_compressed_formats = match app.world().get_resource::<RenderDevice>() {
+            Some(render_device) => CompressedImageFormats::from_features(render_device.features()),
+            None => CompressedImageFormats::NONE,
+        };
+        Self(GltfLoader {
+            supported_compressed_formats,
+            custom_vertex_attributes: Default::default(),
+        })
+    }
+}
+
+impl AssetLoader for VrmLoader {
+    type Asset = Gltf;
+    type Settings = GltfLoaderSettings;
+    type Error = GltfError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        settings: &GltfLoaderSettings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Gltf, Self::Error> {
+        self.0.load(reader, settings, load_context).await
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["vrm"]
+    }
+}
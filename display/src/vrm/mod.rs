@@ -0,0 +1,332 @@
+pub mod expressions;
+pub mod loader;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy::scene::SceneInstanceReady;
+use serde::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::character_control::bone_map::BoneMap;
+use crate::character_control::humanoid::HumanoidBone;
+use crate::vrm::expressions::apply_vrm_expressions;
+use crate::vrm::loader::VrmLoader;
+
+/// Loads `.vrm` avatars and drives their expressions, see [`Vrm`].
+pub struct VrmPlugin;
+
+impl Plugin for VrmPlugin {
+    fn build(&self, app: &mut App) {
+        app.preregister_asset_loader::<VrmLoader>(&["vrm"])
+            .init_resource::<VrmNodes>()
+            .add_observer(find_vrm_nodes)
+            .add_systems(Update, apply_vrm_expressions.run_if(resource_exists::<Vrm>));
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.register_asset_loader(VrmLoader::new(app));
+    }
+}
+
+/// The `VRMC_vrm` extension of the avatar, when it is a VRM 1.0 model.
+///
+/// See <https://github.com/vrm-c/vrm-specification/tree/master/specification/VRMC_vrm-1.0>.
+#[derive(Resource, Debug, Clone)]
+pub struct Vrm {
+    /// Name of every glTF node, as the entities of the scene are named.
+    pub node_names: Vec<String>,
+    pub humanoid: VrmHumanoid,
+    pub expressions: VrmExpressions,
+    pub look_at: Option<VrmLookAt>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VrmExtension {
+    humanoid: VrmHumanoid,
+    #[serde(default)]
+    expressions: VrmExpressions,
+    look_at: Option<VrmLookAt>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VrmHumanoid {
+    /// VRM bone name (`"leftUpperArm"`) to node.
+    pub human_bones: HashMap<String, VrmNodeRef>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct VrmNodeRef {
+    pub node: usize,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VrmExpressions {
+    /// Expressions by preset name (`"blink"`, `"aa"`, `"happy"`, ...). Custom expressions have no
+    /// tracked counterpart and are left alone.
+    #[serde(default)]
+    pub preset: HashMap<String, VrmExpression>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VrmExpression {
+    #[serde(default)]
+    pub morph_target_binds: Vec<VrmMorphTargetBind>,
+    /// Snaps the weight to `0` or `1`.
+    #[serde(default)]
+    pub is_binary: bool,
+}
+
+/// Drives morph target `index` of the mesh on `node` with the expression weight times `weight`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct VrmMorphTargetBind {
+    pub node: usize,
+    pub index: usize,
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VrmLookAtType {
+    /// The eye bones are rotated.
+    #[default]
+    Bone,
+    /// The `lookUp`, `lookDown`, `lookLeft` and `lookRight` expressions are weighted.
+    Expression,
+}
+
+/// How far the eyes turn, in degrees for [`VrmLookAtType::Bone`] or as an expression weight.
+///
+/// Every field is optional in the spec, missing ones take the defaults of the reference loaders.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VrmRangeMap {
+    /// Gaze angle in degrees at which the output is at its full scale, `90` when missing.
+    #[serde(default = "default_input_max_value")]
+    pub input_max_value: f32,
+    /// `10` degrees for bones and a full weight for expressions when missing, see
+    /// [`VrmLookAtType::default_output_scale`].
+    #[serde(default)]
+    pub output_scale: Option<f32>,
+}
+
+fn default_input_max_value() -> f32 {
+    90.0
+}
+
+impl VrmLookAtType {
+    pub fn default_output_scale(self) -> f32 {
+        match self {
+            VrmLookAtType::Bone => 10.0,
+            VrmLookAtType::Expression => 1.0,
+        }
+    }
+}
+
+impl VrmRangeMap {
+    fn map(&self, kind: VrmLookAtType, degrees: f32) -> f32 {
+        if self.input_max_value <= 0.0 {
+            return 0.0;
+        }
+        let output_scale = self
+            .output_scale
+            .unwrap_or_else(|| kind.default_output_scale());
+        (degrees.abs() / self.input_max_value).min(1.0) * output_scale
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VrmLookAt {
+    #[serde(rename = "type", default)]
+    pub kind: VrmLookAtType,
+    /// Towards the nose.
+    #[serde(default)]
+    pub range_map_horizontal_inner: VrmRangeMap,
+    /// Away from the nose.
+    #[serde(default)]
+    pub range_map_horizontal_outer: VrmRangeMap,
+    #[serde(default)]
+    pub range_map_vertical_down: VrmRangeMap,
+    #[serde(default)]
+    pub range_map_vertical_up: VrmRangeMap,
+}
+
+/// Gaze angle in degrees of a `look_x`/`look_y` of `1`, the largest MediaPipe reports.
+const MAX_GAZE_DEGREES: f32 = 30.0;
+
+impl VrmLookAt {
+    /// Yaw and pitch in degrees of each eye for a face looking at `look_x` (towards the
+    /// performer's right) and `look_y` (up), as `(left, right)`.
+    pub fn eye_angles(&self, look_x: f32, look_y: f32) -> ((f32, f32), (f32, f32)) {
+        let yaw = look_x * MAX_GAZE_DEGREES;
+        let pitch = look_y * MAX_GAZE_DEGREES;
+
+        let pitch = if pitch >= 0.0 {
+            self.range_map_vertical_up.map(self.kind, pitch)
+        } else {
+            -self.range_map_vertical_down.map(self.kind, pitch)
+        };
+        // Looking right turns the left eye towards the nose and the right eye away from it
+        let (left_yaw, right_yaw) = if yaw >= 0.0 {
+            (
+                self.range_map_horizontal_inner.map(self.kind, yaw),
+                self.range_map_horizontal_outer.map(self.kind, yaw),
+            )
+        } else {
+            (
+                -self.range_map_horizontal_outer.map(self.kind, yaw),
+                -self.range_map_horizontal_inner.map(self.kind, yaw),
+            )
+        };
+        ((left_yaw, pitch), (right_yaw, pitch))
+    }
+
+    /// Weights of the look expressions, as `(up, down, left, right)`.
+    pub fn expression_weights(&self, look_x: f32, look_y: f32) -> (f32, f32, f32, f32) {
+        let yaw = look_x * MAX_GAZE_DEGREES;
+        let pitch = look_y * MAX_GAZE_DEGREES;
+        let (up, down) = if pitch >= 0.0 {
+            (self.range_map_vertical_up.map(self.kind, pitch), 0.0)
+        } else {
+            (0.0, self.range_map_vertical_down.map(self.kind, pitch))
+        };
+        let (left, right) = if yaw >= 0.0 {
+            (0.0, self.range_map_horizontal_outer.map(self.kind, yaw))
+        } else {
+            (self.range_map_horizontal_outer.map(self.kind, yaw), 0.0)
+        };
+        (up, down, left, right)
+    }
+}
+
+impl Vrm {
+    /// Reads the VRM extension out of the JSON chunk of a `.vrm` (binary glTF) file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes =
+            fs::read(path).map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        let json =
+            glb_json(&bytes).map_err(|err| format!("Invalid {}: {}", path.display(), err))?;
+
+        let node_names = json["nodes"]
+            .as_array()
+            .map(|nodes| {
+                nodes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, node)| {
+                        node["name"]
+                            .as_str()
+                            .map(str::to_string)
+                            .unwrap_or_else(|| format!("GltfNode{}", i))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let extensions = &json["extensions"];
+        if extensions["VRMC_vrm"].is_null() {
+            return Err(if extensions["VRM"].is_null() {
+                format!("{} is not a VRM model", path.display())
+            } else {
+                format!(
+                    "{} is a VRM 0.x model, only VRM 1.0 is supported",
+                    path.display()
+                )
+            });
+        }
+        let extension: VrmExtension = serde_json::from_value(extensions["VRMC_vrm"].clone())
+            .map_err(|err| format!("Invalid VRMC_vrm in {}: {}", path.display(), err))?;
+
+        Ok(Self {
+            node_names,
+            humanoid: extension.humanoid,
+            expressions: extension.expressions,
+            look_at: extension.look_at,
+        })
+    }
+
+    /// Maps every humanoid bone of the model onto its node name.
+    pub fn bone_map(&self) -> BoneMap {
+        let mut bone_map = BoneMap::default();
+        for bone in HumanoidBone::iter() {
+            let Some(name) = vrm_bone_name(bone) else {
+                continue;
+            };
+            let node_name = self
+                .humanoid
+                .human_bones
+                .get(&name)
+                .and_then(|bone| self.node_names.get(bone.node));
+            if let Some(node_name) = node_name {
+                bone_map.bones.insert(bone, node_name.clone());
+            }
+        }
+        bone_map
+    }
+}
+
+/// The VRM name of a humanoid bone, `None` for bones VRM does not have.
+fn vrm_bone_name(bone: HumanoidBone) -> Option<String> {
+    let name = bone.to_string();
+    if name.ends_with("LowerArmTwist") {
+        return None;
+    }
+    // VRM 1.0 names the thumb joints metacarpal, proximal and distal
+    let name = name.replace("ThumbIntermediate", "ThumbProximal");
+
+    let mut chars = name.chars();
+    let first = chars.next()?;
+    Some(first.to_ascii_lowercase().to_string() + chars.as_str())
+}
+
+/// The JSON chunk of a binary glTF, which always comes first.
+fn glb_json(bytes: &[u8]) -> Result<serde_json::Value, String> {
+    const JSON_CHUNK: u32 = 0x4E4F534A;
+
+    let read_u32 = |offset: usize| {
+        bytes
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    if bytes.get(0..4) != Some(b"glTF".as_slice()) {
+        return Err("not a binary glTF".to_string());
+    }
+    let length = read_u32(12).ok_or("truncated header")? as usize;
+    if read_u32(16) != Some(JSON_CHUNK) {
+        return Err("the first chunk is not JSON".to_string());
+    }
+    let chunk = bytes.get(20..20 + length).ok_or("truncated JSON chunk")?;
+    serde_json::from_slice(chunk).map_err(|err| err.to_string())
+}
+
+/// Entities of the glTF nodes the VRM extension refers to, by node index.
+#[derive(Resource, Default, Debug)]
+pub struct VrmNodes(pub HashMap<usize, Entity>);
+
+fn find_vrm_nodes(
+    _trigger: Trigger<SceneInstanceReady>,
+    vrm: Option<Res<Vrm>>,
+    query: Query<(Entity, &Name)>,
+    mut nodes: ResMut<VrmNodes>,
+) {
+    let Some(vrm) = vrm else {
+        return;
+    };
+    let indices: HashMap<&str, usize> = vrm
+        .node_names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect();
+    for (entity, name) in &query {
+        if let Some(&index) = indices.get(name.as_str()) {
+            nodes.0.insert(index, entity);
+        }
+    }
+}
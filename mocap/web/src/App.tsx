@@ -1,4 +1,4 @@
-import { Accessor, children, createEffect, createSignal, For, JSX, JSXElement, Setter, Show, type Component } from 'solid-js';
+import { Accessor, children, createEffect, createSignal, For, JSX, JSXElement, onCleanup, Setter, Show, type Component } from 'solid-js';
 import { PoseLandmarker, FilesetResolver, FaceLandmarker, DrawingUtils, HandLandmarker, PoseLandmarkerResult, HandLandmarkerResult, FaceLandmarkerResult } from "@mediapipe/tasks-vision"
 import { Switch } from '@kobalte/core/switch';
 import CameraIcon from './assets/icons/camera-photo-symbolic.svg?component-solid';
@@ -249,79 +249,73 @@ const App: Component = () => {
     window.requestAnimationFrame(predictWebcam);
   }
 
-  createEffect(async () => {
+  // Frames go over the /stream socket while it is open, and are POSTed to /set_* otherwise.
+  const [socket, setSocket] = createSignal<WebSocket>();
+
+  createEffect(() => {
+    if (!urlValid()) {
+      return;
+    }
+    const ws = new WebSocket(apiUrl().replace(/^http/, "ws") + "/stream");
+    ws.onopen = () => setSocket(ws);
+    ws.onmessage = (event) => {
+      const reply = JSON.parse(event.data);
+      if (reply.type === "error") {
+        console.error(`Stream error (${reply.stream ?? "unknown"}):`, reply.message);
+      }
+    };
+    ws.onclose = () => {
+      if (socket() === ws) {
+        setSocket(undefined);
+      }
+    };
+    onCleanup(() => ws.close());
+  });
+
+  async function sendFrame(type: "pose" | "hands" | "face", body: object) {
+    const ws = socket();
+    if (ws && ws.readyState === WebSocket.OPEN) {
+      ws.send(JSON.stringify({ type, ...body }));
+      return;
+    }
+    try {
+      const response = await fetch(apiUrl() + "/set_" + type, {
+        method: 'POST',
+        headers: {
+          'Content-Type': 'application/json',
+        },
+        body: JSON.stringify(body),
+      });
+      if (!response.ok) {
+        console.error(`Failed to send ${type} data. Status: ${response.status}`);
+        const errorText = await response.text();
+        console.error('Error details:', errorText);
+      }
+    } catch (error) {
+      console.error(`An error occurred while sending ${type} data:`, error);
+    }
+  }
+
+  createEffect(() => {
     const poseRes = poseLandmarkerResult();
     if (poseRes && urlValid()) {
-      try {
-        const response = await fetch(apiUrl() + "/set_pose", {
-          method: 'POST', // Specify the method
-          headers: {
-            'Content-Type': 'application/json', // Indicate that the body is JSON
-          },
-          body: JSON.stringify({ poseLandmarkerResult: poseRes }), // Convert the JavaScript object to a JSON string
-        });
-
-        // Check if the request was successful (status code 2xx)
-        if (response.ok) {
-          // console.log('Pose data sent successfully!');
-        } else {
-          console.error(`Failed to send pose data. Status: ${response.status}`);
-          const errorText = await response.text();
-          console.error('Error details:', errorText);
-        }
-      } catch (error) {
-        console.error('An error occurred while sending pose data:', error);
-      }
+      sendFrame("pose", { poseLandmarkerResult: poseRes });
     }
   });
 
-  createEffect(async () => {
+  createEffect(() => {
     const handRes = handLandmarkerResult();
-
-    // console.log("HandRes", handRes);
     if (handRes && urlValid()) {
-      try {
-
-        const response = await fetch(apiUrl() + "/set_hands", {
-          method: "POST",
-          headers: {
-            "Content-Type": "application/json",
-          },
-          body: JSON.stringify({ handLandmarkerResult: handRes }),
-        });
-        if (response.ok) {
-          // console.log('Hand data sent successfully!');
-        } else {
-          console.error(`Failed to send hand data. Status: ${response.status}`);
-        }
-      } catch (error) {
-        console.error('An error occurred while sending hand data:', error);
-      }
+      sendFrame("hands", { handLandmarkerResult: handRes });
     }
   });
 
-  createEffect(async () => {
-    const faceRes = faceLandmarkerResult()
-    console.log(faceRes)
+  createEffect(() => {
+    const faceRes = faceLandmarkerResult();
     if (faceRes && urlValid()) {
-      try {
-        const response = await fetch(apiUrl() + "/set_face", {
-          method: "POST",
-          headers: {
-            "Content-Type": "application/json",
-          },
-          body: JSON.stringify({ faceLandmarkerResult: faceRes })
-        })
-        if (response.ok) {
-          // console.log('Hand data sent successfully!');
-        } else {
-          console.error(`Failed to send face data. Status: ${response.status}`, response);
-        }
-      } catch (error) {
-        console.error('An error occurred while sending face data:', error);
-      }
+      sendFrame("face", { faceLandmarkerResult: faceRes });
     }
-  })
+  });
 
 
   async function createModels() {